@@ -0,0 +1,489 @@
+//! Implements an append-only `DbInterface` storing its lines in a log file.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{BufReader, ErrorKind, Read, Seek, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use tracing::warn;
+
+use super::{
+    DbInterface, EncryptedValue, Token, TokenToEncryptedValueMap, TokenWithEncryptedValueList,
+    Tokens,
+};
+use crate::DbInterfaceErrorTrait;
+
+/// Error returned by a log database.
+#[derive(Debug)]
+pub enum LogDbError {
+    /// The log file could not be read or written.
+    Io(std::io::Error),
+    /// The log file contains a malformed record.
+    Corrupted(String),
+    /// An upsert is guarded by a value for a token having no value.
+    MissingLine(Token),
+    /// An insertion targets a token already having a value.
+    ExistingLine(Token),
+}
+
+impl Display for LogDbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "log I/O error: {err}"),
+            Self::Corrupted(msg) => write!(f, "corrupted log: {msg}"),
+            Self::MissingLine(token) => write!(f, "no line stored for token {token}"),
+            Self::ExistingLine(token) => write!(f, "a line is already stored for token {token}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for LogDbError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl std::error::Error for LogDbError {}
+impl DbInterfaceErrorTrait for LogDbError {}
+
+/// Record of the log: the value written for a token, or `None` if this
+/// token was deleted.
+pub type LogRecord<const VALUE_LENGTH: usize> = (u64, Token, Option<EncryptedValue<VALUE_LENGTH>>);
+
+/// Record flag marking a written value.
+const VALUE_FLAG: u8 = 1;
+
+/// Record flag marking a deletion.
+const DELETION_FLAG: u8 = 0;
+
+fn serialize_record<const VALUE_LENGTH: usize>(
+    record: &mut Vec<u8>,
+    (sequence, token, value): &LogRecord<VALUE_LENGTH>,
+) {
+    record.extend(sequence.to_le_bytes());
+    record.extend(&**token);
+    if let Some(value) = value {
+        record.push(VALUE_FLAG);
+        record.extend(Vec::from(value));
+    } else {
+        record.push(DELETION_FLAG);
+    }
+}
+
+/// Reads the next record of the log, or returns `None` at the end of the log.
+///
+/// A record truncated by the end of the log, e.g. by an interrupted write, is
+/// considered as the end of the log.
+fn read_record<const VALUE_LENGTH: usize>(
+    reader: &mut impl Read,
+) -> Result<Option<LogRecord<VALUE_LENGTH>>, LogDbError> {
+    let read_exact = |reader: &mut dyn Read, buffer: &mut [u8]| match reader.read_exact(buffer) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        res => res.map(|()| true),
+    };
+    let mut sequence = [0; 8];
+    let mut token = [0; Token::LENGTH];
+    let mut flag = [0; 1];
+    if !(read_exact(reader, &mut sequence)?
+        && read_exact(reader, &mut token)?
+        && read_exact(reader, &mut flag)?)
+    {
+        return Ok(None);
+    }
+    let value = match flag[0] {
+        VALUE_FLAG => {
+            let mut value = vec![0; EncryptedValue::<VALUE_LENGTH>::LENGTH];
+            if !read_exact(reader, &mut value)? {
+                return Ok(None);
+            }
+            Some(
+                EncryptedValue::try_from(value.as_slice())
+                    .map_err(|e| LogDbError::Corrupted(e.to_string()))?,
+            )
+        }
+        DELETION_FLAG => None,
+        flag => return Err(LogDbError::Corrupted(format!("unknown record flag {flag}"))),
+    };
+    Ok(Some((
+        u64::from_le_bytes(sequence),
+        Token::from(token),
+        value,
+    )))
+}
+
+#[derive(Debug)]
+struct Log<const VALUE_LENGTH: usize> {
+    file: File,
+    length: u64,
+    next_sequence: u64,
+    latest: HashMap<Token, EncryptedValue<VALUE_LENGTH>>,
+}
+
+impl<const VALUE_LENGTH: usize> Log<VALUE_LENGTH> {
+    /// Appends the records of the given modifications in a single write.
+    ///
+    /// Upon failure, the log is truncated back to its previous length so that
+    /// none of these modifications is applied.
+    fn append(
+        &mut self,
+        modifications: Vec<(Token, Option<EncryptedValue<VALUE_LENGTH>>)>,
+    ) -> Result<(), LogDbError> {
+        if modifications.is_empty() {
+            return Ok(());
+        }
+        let records = modifications
+            .into_iter()
+            .zip(self.next_sequence..)
+            .map(|((token, value), sequence)| (sequence, token, value))
+            .collect::<Vec<_>>();
+        let mut bytes = Vec::new();
+        for record in &records {
+            serialize_record(&mut bytes, record);
+        }
+        if let Err(err) = self.file.write_all(&bytes) {
+            if let Err(truncate_err) = self.file.set_len(self.length) {
+                warn!("could not truncate the log after a failed write: {truncate_err}");
+            }
+            return Err(err.into());
+        }
+        self.length += bytes.len() as u64;
+        self.next_sequence += records.len() as u64;
+        for (_, token, value) in records {
+            match value {
+                Some(value) => self.latest.insert(token, value),
+                None => self.latest.remove(&token),
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Append-only database, e.g. for auditable storage.
+///
+/// Each modification appends a sequenced record to the log file: lines are
+/// never overwritten, and deletions are recorded as tombstones. The latest
+/// value of each token is kept in memory to serve the fetches, and the
+/// conditional upserts compare the guards to it.
+///
+/// The records of an operation are appended in a single write. A record left
+/// incomplete by an interrupted write is discarded when the log is opened.
+///
+/// The log grows with each modification, compactions included, until it is
+/// rewritten by the application.
+#[derive(Debug)]
+pub struct LogDb<const VALUE_LENGTH: usize> {
+    path: PathBuf,
+    log: Mutex<Log<VALUE_LENGTH>>,
+}
+
+impl<const VALUE_LENGTH: usize> LogDb<VALUE_LENGTH> {
+    /// Opens the log stored at the given path, creating it if needed, and
+    /// replays it to find the latest value of each token.
+    ///
+    /// An incomplete record at the end of the log is removed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LogDbError> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+        let (records, length) = Self::read_log(&path)?;
+        if length < file.metadata()?.len() {
+            warn!("removing the incomplete record at the end of the log {path:?}");
+            file.set_len(length)?;
+        }
+        let mut log = Log {
+            file,
+            length,
+            next_sequence: 0,
+            latest: HashMap::new(),
+        };
+        for (sequence, token, value) in records {
+            log.next_sequence = sequence + 1;
+            match value {
+                Some(value) => log.latest.insert(token, value),
+                None => log.latest.remove(&token),
+            };
+        }
+        Ok(Self {
+            path,
+            log: Mutex::new(log),
+        })
+    }
+
+    /// Returns all the records of the log, in order.
+    pub fn records(&self) -> Result<Vec<LogRecord<VALUE_LENGTH>>, LogDbError> {
+        // Prevents reading a record being written.
+        let _log = self.log.lock().expect("could not lock mutex");
+        Self::read_log(&self.path).map(|(records, _)| records)
+    }
+
+    /// Reads the complete records of the log, and returns them along with the
+    /// length of the log they span.
+    fn read_log(path: &Path) -> Result<(Vec<LogRecord<VALUE_LENGTH>>, u64), LogDbError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut records = Vec::new();
+        let mut length = 0;
+        while let Some(record) = read_record(&mut reader)? {
+            records.push(record);
+            length = reader.stream_position()?;
+        }
+        Ok((records, length))
+    }
+}
+
+#[async_trait(?Send)]
+impl<const VALUE_LENGTH: usize> DbInterface<VALUE_LENGTH> for LogDb<VALUE_LENGTH> {
+    type Error = LogDbError;
+
+    async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+        Ok(self
+            .log
+            .lock()
+            .expect("could not lock mutex")
+            .latest
+            .keys()
+            .copied()
+            .collect())
+    }
+
+    async fn fetch(
+        &self,
+        tokens: Tokens,
+    ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+        let log = self.log.lock().expect("could not lock mutex");
+        Ok(tokens
+            .into_iter()
+            .filter_map(|token| log.latest.get(&token).map(|value| (token, value.clone())))
+            .collect())
+    }
+
+    async fn upsert(
+        &self,
+        old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+        let mut log = self.log.lock().expect("could not lock mutex");
+        if let Some(token) = old_values
+            .keys()
+            .find(|token| !log.latest.contains_key(token))
+        {
+            return Err(LogDbError::MissingLine(*token));
+        }
+        let mut rejected_values = HashMap::new();
+        let mut modifications = Vec::new();
+        for (token, new_value) in new_values {
+            let latest_value = log.latest.get(&token);
+            if old_values.get(&token) == latest_value {
+                modifications.push((token, Some(new_value)));
+            } else if let Some(latest_value) = latest_value {
+                rejected_values.insert(token, latest_value.clone());
+            }
+        }
+        log.append(modifications)?;
+        Ok(TokenToEncryptedValueMap::from(rejected_values))
+    }
+
+    async fn insert(
+        &self,
+        values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<(), Self::Error> {
+        let mut log = self.log.lock().expect("could not lock mutex");
+        if let Some(token) = values.keys().find(|token| log.latest.contains_key(token)) {
+            return Err(LogDbError::ExistingLine(*token));
+        }
+        log.append(
+            values
+                .into_iter()
+                .map(|(token, value)| (token, Some(value)))
+                .collect(),
+        )
+    }
+
+    async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+        let mut log = self.log.lock().expect("could not lock mutex");
+        let deletions = tokens
+            .into_iter()
+            .filter(|token| log.latest.contains_key(token))
+            .map(|token| (token, None))
+            .collect();
+        log.append(deletions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmian_crypto_core::{
+        reexport::rand_core::{RngCore, SeedableRng},
+        CsRng,
+    };
+
+    use super::*;
+    use crate::edx::in_memory::tests::{random_line, VALUE_LENGTH};
+
+    fn temp_path(rng: &mut CsRng) -> PathBuf {
+        std::env::temp_dir().join(format!("findex_log_{}", rng.next_u64()))
+    }
+
+    crate::db_interface_conformance_tests!(
+        LogDb<VALUE_LENGTH>,
+        LogDb::open(temp_path(&mut CsRng::from_entropy())).unwrap()
+    );
+
+    #[actix_rt::test]
+    async fn test_append_only_log() {
+        let mut rng = CsRng::from_entropy();
+        let path = temp_path(&mut rng);
+        let db = LogDb::<VALUE_LENGTH>::open(&path).unwrap();
+
+        let (token, value) = random_line(&mut rng);
+        let (_, new_value) = random_line(&mut rng);
+        db.insert(TokenToEncryptedValueMap::from_iter([(
+            token,
+            value.clone(),
+        )]))
+        .await
+        .unwrap();
+        let rejected = db
+            .upsert(
+                TokenToEncryptedValueMap::from_iter([(token, value.clone())]),
+                TokenToEncryptedValueMap::from_iter([(token, new_value.clone())]),
+            )
+            .await
+            .unwrap();
+        assert!(rejected.is_empty());
+
+        // The guard is compared to the latest value.
+        let rejected = db
+            .upsert(
+                TokenToEncryptedValueMap::from_iter([(token, value.clone())]),
+                TokenToEncryptedValueMap::from_iter([(token, value.clone())]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            rejected,
+            TokenToEncryptedValueMap::from_iter([(token, new_value.clone())])
+        );
+
+        let res = db.fetch(Tokens::from_iter([token])).await.unwrap();
+        assert_eq!(res.0, vec![(token, new_value.clone())]);
+
+        // Older versions remain in the log.
+        assert_eq!(
+            db.records().unwrap(),
+            vec![
+                (0, token, Some(value.clone())),
+                (1, token, Some(new_value.clone()))
+            ]
+        );
+
+        db.delete(Tokens::from_iter([token])).await.unwrap();
+        assert!(db
+            .fetch(Tokens::from_iter([token]))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // The log is replayed upon opening.
+        let (other_token, other_value) = random_line(&mut rng);
+        db.insert(TokenToEncryptedValueMap::from_iter([(
+            other_token,
+            other_value.clone(),
+        )]))
+        .await
+        .unwrap();
+        drop(db);
+        let db = LogDb::<VALUE_LENGTH>::open(&path).unwrap();
+        assert_eq!(db.records().unwrap().len(), 4);
+        assert_eq!(
+            db.dump_tokens().await.unwrap(),
+            Tokens::from_iter([other_token])
+        );
+        db.delete(Tokens::from_iter([other_token])).await.unwrap();
+        assert_eq!(db.records().unwrap()[4], (4, other_token, None));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_insert_existing_token() {
+        let mut rng = CsRng::from_entropy();
+        let path = temp_path(&mut rng);
+        let db = LogDb::<VALUE_LENGTH>::open(&path).unwrap();
+
+        let (token, value) = random_line(&mut rng);
+        let (other_token, other_value) = random_line(&mut rng);
+        db.insert(TokenToEncryptedValueMap::from_iter([(
+            token,
+            value.clone(),
+        )]))
+        .await
+        .unwrap();
+
+        // No line is written if one of the tokens already has a value.
+        let res = db
+            .insert(TokenToEncryptedValueMap::from_iter([
+                (token, other_value.clone()),
+                (other_token, other_value),
+            ]))
+            .await;
+        assert!(
+            matches!(res, Err(LogDbError::ExistingLine(existing)) if existing == token),
+            "{res:?}"
+        );
+        assert_eq!(db.records().unwrap(), vec![(0, token, Some(value))]);
+        assert_eq!(db.dump_tokens().await.unwrap(), Tokens::from_iter([token]));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_incomplete_record() {
+        let mut rng = CsRng::from_entropy();
+        let path = temp_path(&mut rng);
+        let db = LogDb::<VALUE_LENGTH>::open(&path).unwrap();
+
+        let (token, value) = random_line(&mut rng);
+        db.insert(TokenToEncryptedValueMap::from_iter([(
+            token,
+            value.clone(),
+        )]))
+        .await
+        .unwrap();
+        drop(db);
+
+        // Simulates a write interrupted in the middle of a record.
+        let length = std::fs::metadata(&path).unwrap().len();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[1; 8 + Token::LENGTH + 1 + 3]).unwrap();
+        drop(file);
+
+        let db = LogDb::<VALUE_LENGTH>::open(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), length);
+        assert_eq!(db.records().unwrap(), vec![(0, token, Some(value.clone()))]);
+
+        // New records are appended after the last complete one.
+        let (other_token, other_value) = random_line(&mut rng);
+        db.insert(TokenToEncryptedValueMap::from_iter([(
+            other_token,
+            other_value.clone(),
+        )]))
+        .await
+        .unwrap();
+        drop(db);
+        let db = LogDb::<VALUE_LENGTH>::open(&path).unwrap();
+        assert_eq!(
+            db.records().unwrap(),
+            vec![(0, token, Some(value)), (1, other_token, Some(other_value))]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,294 @@
+//! Implements a `DbInterface` adapter recording the operations performed on
+//! the wrapped database.
+//!
+//! Records only contain the number of tokens involved in each operation, never
+//! the tokens or the values themselves.
+
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use async_trait::async_trait;
+
+use super::{DbInterface, TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens};
+
+/// Operation performed on an audited database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuditedOperation {
+    DumpTokens,
+    Fetch,
+    Upsert,
+    Insert,
+    Delete,
+}
+
+/// Outcome of an operation performed on an audited database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditOutcome {
+    /// All modifications were applied.
+    Success,
+    /// The upsert was rejected for the given number of tokens since their
+    /// stored value did not match the given old value.
+    Rejected(usize),
+    /// The database returned an error.
+    Failure(String),
+}
+
+/// Record of an operation performed on an audited database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub timestamp: SystemTime,
+    pub operation: AuditedOperation,
+    pub n_tokens: usize,
+    pub outcome: AuditOutcome,
+}
+
+/// Destination of the audit records.
+pub trait AuditSink {
+    /// Stores the given record. This is called before the result of the
+    /// audited operation is returned.
+    fn record(&self, record: AuditRecord);
+}
+
+/// Audit sink storing the records in memory.
+#[derive(Debug, Clone, Default)]
+pub struct VecSink(Arc<Mutex<Vec<AuditRecord>>>);
+
+impl VecSink {
+    /// Returns a copy of the records stored so far.
+    #[must_use]
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.0.lock().expect("could not lock mutex").clone()
+    }
+}
+
+impl AuditSink for VecSink {
+    fn record(&self, record: AuditRecord) {
+        self.0.lock().expect("could not lock mutex").push(record);
+    }
+}
+
+/// Database recording the operations performed on the wrapped database into
+/// the given sink.
+///
+/// Modifications are always recorded. Fetches and token dumps are only
+/// recorded if `audit_fetch` is set to `true`.
+#[derive(Debug)]
+pub struct AuditedDb<Db, Sink: AuditSink> {
+    db: Db,
+    sink: Sink,
+    audit_fetch: bool,
+}
+
+impl<Db, Sink: AuditSink> AuditedDb<Db, Sink> {
+    pub const fn new(db: Db, sink: Sink, audit_fetch: bool) -> Self {
+        Self {
+            db,
+            sink,
+            audit_fetch,
+        }
+    }
+
+    /// Records the given operation along with the outcome derived from the
+    /// given result.
+    fn audit<T, E: Display>(
+        &self,
+        operation: AuditedOperation,
+        n_tokens: usize,
+        res: &Result<T, E>,
+        get_outcome: impl FnOnce(&T) -> AuditOutcome,
+    ) {
+        let outcome = match res {
+            Ok(value) => get_outcome(value),
+            Err(err) => AuditOutcome::Failure(err.to_string()),
+        };
+        self.sink.record(AuditRecord {
+            timestamp: SystemTime::now(),
+            operation,
+            n_tokens,
+            outcome,
+        });
+    }
+}
+
+#[async_trait(?Send)]
+impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>, Sink: AuditSink>
+    DbInterface<VALUE_LENGTH> for AuditedDb<Db, Sink>
+{
+    type Error = Db::Error;
+
+    async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+        let res = self.db.dump_tokens().await;
+        if self.audit_fetch {
+            let n_tokens = res.as_ref().map_or(0, |tokens| tokens.len());
+            self.audit(AuditedOperation::DumpTokens, n_tokens, &res, |_| {
+                AuditOutcome::Success
+            });
+        }
+        res
+    }
+
+    async fn fetch(
+        &self,
+        tokens: Tokens,
+    ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+        let n_tokens = tokens.len();
+        let res = self.db.fetch(tokens).await;
+        if self.audit_fetch {
+            self.audit(AuditedOperation::Fetch, n_tokens, &res, |_| {
+                AuditOutcome::Success
+            });
+        }
+        res
+    }
+
+    async fn upsert(
+        &self,
+        old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+        let n_tokens = new_values.len();
+        let res = self.db.upsert(old_values, new_values).await;
+        self.audit(AuditedOperation::Upsert, n_tokens, &res, |rejected| {
+            if rejected.is_empty() {
+                AuditOutcome::Success
+            } else {
+                AuditOutcome::Rejected(rejected.len())
+            }
+        });
+        res
+    }
+
+    async fn insert(
+        &self,
+        values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<(), Self::Error> {
+        let n_tokens = values.len();
+        let res = self.db.insert(values).await;
+        self.audit(AuditedOperation::Insert, n_tokens, &res, |()| {
+            AuditOutcome::Success
+        });
+        res
+    }
+
+    async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+        let n_tokens = tokens.len();
+        let res = self.db.delete(tokens).await;
+        self.audit(AuditedOperation::Delete, n_tokens, &res, |()| {
+            AuditOutcome::Success
+        });
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use cosmian_crypto_core::{reexport::rand_core::SeedableRng, CsRng};
+
+    use super::*;
+    use crate::{
+        edx::{
+            chain_table::ChainTable,
+            entry_table::EntryTable,
+            in_memory::{tests::VALUE_LENGTH, InMemoryDb},
+        },
+        Data, DxEnc, Findex, Index, IndexedValue, IndexedValueToKeywordsMap, Keyword, Keywords,
+        Label,
+    };
+
+    crate::db_interface_conformance_tests!(
+        AuditedDb<InMemoryDb<VALUE_LENGTH>, VecSink>,
+        AuditedDb::new(InMemoryDb::default(), VecSink::default(), true)
+    );
+
+    #[actix_rt::test]
+    async fn test_audit() {
+        let mut rng = CsRng::from_entropy();
+        let sink = VecSink::default();
+
+        let findex = Findex::new(
+            EntryTable::setup(AuditedDb::new(InMemoryDb::default(), sink.clone(), false)),
+            ChainTable::setup(InMemoryDb::default()),
+        );
+        let key = findex.keygen();
+        let label = Label::random(&mut rng);
+        let keyword = Keyword::from("keyword");
+
+        for i in 0..2 {
+            findex
+                .add(
+                    &key,
+                    &label,
+                    IndexedValueToKeywordsMap::from_iter([(
+                        IndexedValue::Data(Data::from(format!("location {i}").as_str())),
+                        HashSet::from_iter([keyword.clone()]),
+                    )]),
+                )
+                .await
+                .unwrap();
+        }
+
+        // Write a concurrent entry directly into the Entry Table and check the
+        // rejection is recorded.
+        let (token, value) = findex
+            .findex_graph
+            .findex_mm
+            .entry_table
+            .0
+            .db
+            .lock()
+            .unwrap()
+            .iter()
+            .next()
+            .map(|(token, value)| (*token, value.clone()))
+            .unwrap();
+        let res = findex
+            .findex_graph
+            .findex_mm
+            .entry_table
+            .0
+            .upsert(
+                TokenToEncryptedValueMap::default(),
+                TokenToEncryptedValueMap::from_iter([(token, value)]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(1, res.len());
+
+        findex
+            .findex_graph
+            .findex_mm
+            .entry_table
+            .0
+            .delete(Tokens::from_iter([token]))
+            .await
+            .unwrap();
+
+        // Fetches are not audited.
+        findex
+            .search(&key, &label, Keywords::from_iter([keyword]), &|_| async {
+                Ok(false)
+            })
+            .await
+            .unwrap();
+
+        let records = sink
+            .records()
+            .into_iter()
+            .map(|record| (record.operation, record.n_tokens, record.outcome))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            records,
+            vec![
+                (AuditedOperation::Upsert, 1, AuditOutcome::Success),
+                (AuditedOperation::Upsert, 1, AuditOutcome::Success),
+                (AuditedOperation::Upsert, 1, AuditOutcome::Rejected(1)),
+                (AuditedOperation::Delete, 1, AuditOutcome::Success),
+            ]
+        );
+    }
+}
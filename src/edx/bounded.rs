@@ -0,0 +1,212 @@
+//! Implements a `DbInterface` adapter bounding the number of lines stored in
+//! the wrapped database.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+
+use super::{DbInterface, Token, TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens};
+
+/// Lines written through a bounded database, ordered by last write.
+#[derive(Debug, Default)]
+struct WriteOrder {
+    next_stamp: u64,
+    stamps: HashMap<Token, u64>,
+    tokens: BTreeMap<u64, Token>,
+    n_evicted: usize,
+}
+
+impl WriteOrder {
+    fn written(&mut self, token: Token) {
+        if let Some(stamp) = self.stamps.insert(token, self.next_stamp) {
+            self.tokens.remove(&stamp);
+        }
+        self.tokens.insert(self.next_stamp, token);
+        self.next_stamp += 1;
+    }
+
+    fn deleted(&mut self, token: &Token) {
+        if let Some(stamp) = self.stamps.remove(token) {
+            self.tokens.remove(&stamp);
+        }
+    }
+
+    /// Removes and returns the least-recently-written tokens exceeding the
+    /// given capacity.
+    fn evict(&mut self, capacity: usize) -> Tokens {
+        let mut evicted = Tokens::from_iter([]);
+        while capacity < self.stamps.len() {
+            let Some((_, token)) = self.tokens.pop_first() else {
+                break;
+            };
+            self.stamps.remove(&token);
+            evicted.0.insert(token);
+        }
+        self.n_evicted += evicted.len();
+        evicted
+    }
+}
+
+/// Database storing at most a given number of lines, modelling a
+/// constrained cache-like store.
+///
+/// Once the capacity is exceeded by a write, the least-recently-written
+/// lines are deleted from the wrapped database. Only the lines written
+/// through this database are accounted for. Evicted lines are lost: this is
+/// meant to test the behavior of Findex near storage limits, not to store an
+/// index.
+#[derive(Debug)]
+pub struct BoundedDb<Db> {
+    db: Db,
+    capacity: usize,
+    order: Mutex<WriteOrder>,
+}
+
+impl<Db> BoundedDb<Db> {
+    pub fn new(db: Db, capacity: usize) -> Self {
+        Self {
+            db,
+            capacity,
+            order: Mutex::new(WriteOrder::default()),
+        }
+    }
+
+    /// Returns the number of lines evicted since the instantiation of this
+    /// database.
+    pub fn n_evicted(&self) -> usize {
+        self.order.lock().expect("could not lock mutex").n_evicted
+    }
+
+    /// Records the given written tokens and returns the tokens to evict.
+    fn written(&self, tokens: impl IntoIterator<Item = Token>) -> Tokens {
+        let mut order = self.order.lock().expect("could not lock mutex");
+        for token in tokens {
+            order.written(token);
+        }
+        order.evict(self.capacity)
+    }
+}
+
+#[async_trait(?Send)]
+impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> DbInterface<VALUE_LENGTH>
+    for BoundedDb<Db>
+{
+    type Error = Db::Error;
+
+    async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+        self.db.dump_tokens().await
+    }
+
+    async fn fetch(
+        &self,
+        tokens: Tokens,
+    ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+        self.db.fetch(tokens).await
+    }
+
+    async fn upsert(
+        &self,
+        old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+        let tokens = new_values.keys().copied().collect::<Vec<_>>();
+        let rejected_values = self.db.upsert(old_values, new_values).await?;
+        let evicted = self.written(
+            tokens
+                .into_iter()
+                .filter(|token| !rejected_values.contains_key(token)),
+        );
+        if !evicted.is_empty() {
+            self.db.delete(evicted).await?;
+        }
+        Ok(rejected_values)
+    }
+
+    async fn insert(
+        &self,
+        values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<(), Self::Error> {
+        let tokens = values.keys().copied().collect::<Vec<_>>();
+        self.db.insert(values).await?;
+        let evicted = self.written(tokens);
+        if evicted.is_empty() {
+            Ok(())
+        } else {
+            self.db.delete(evicted).await
+        }
+    }
+
+    async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+        {
+            let mut order = self.order.lock().expect("could not lock mutex");
+            for token in &*tokens {
+                order.deleted(token);
+            }
+        }
+        self.db.delete(tokens).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmian_crypto_core::{reexport::rand_core::SeedableRng, CsRng};
+
+    use super::*;
+    use crate::edx::in_memory::{
+        tests::{random_line, VALUE_LENGTH},
+        InMemoryDb,
+    };
+
+    crate::db_interface_conformance_tests!(
+        BoundedDb<InMemoryDb<VALUE_LENGTH>>,
+        BoundedDb::new(InMemoryDb::default(), 16)
+    );
+
+    #[actix_rt::test]
+    async fn test_eviction() {
+        let mut rng = CsRng::from_entropy();
+        let inner = InMemoryDb::<VALUE_LENGTH>::default();
+        let db = BoundedDb::new(inner.clone(), 3);
+
+        let lines = (0..5).map(|_| random_line(&mut rng)).collect::<Vec<_>>();
+        for line in &lines[..3] {
+            db.insert(TokenToEncryptedValueMap::from_iter([line.clone()]))
+                .await
+                .unwrap();
+        }
+        assert_eq!(0, db.n_evicted());
+
+        // Rewriting the oldest line makes it the most recent one.
+        let (_, new_value) = random_line(&mut rng);
+        let rejected = db
+            .upsert(
+                TokenToEncryptedValueMap::from_iter([lines[0].clone()]),
+                TokenToEncryptedValueMap::from_iter([(lines[0].0, new_value.clone())]),
+            )
+            .await
+            .unwrap();
+        assert!(rejected.is_empty());
+
+        for line in &lines[3..] {
+            db.insert(TokenToEncryptedValueMap::from_iter([line.clone()]))
+                .await
+                .unwrap();
+        }
+        assert_eq!(2, db.n_evicted());
+        assert_eq!(3, inner.len());
+
+        let res = db
+            .fetch(lines.iter().map(|(token, _)| *token).collect())
+            .await
+            .unwrap()
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        assert_eq!(
+            res,
+            HashMap::from_iter([(lines[0].0, new_value), lines[3].clone(), lines[4].clone()])
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! Implements a `DbInterface` adapter caching the values fetched from the
+//! wrapped database.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+
+use super::{
+    DbInterface, EncryptedValue, Token, TokenToEncryptedValueMap, TokenWithEncryptedValueList,
+    Tokens,
+};
+
+/// Least-recently-used cache of database lines.
+#[derive(Debug)]
+struct Lru<const VALUE_LENGTH: usize> {
+    capacity: usize,
+    next_stamp: u64,
+    /// Cached values, along with the stamp of their last use.
+    values: HashMap<Token, (u64, EncryptedValue<VALUE_LENGTH>)>,
+    /// Cached tokens, ordered by last use.
+    order: BTreeMap<u64, Token>,
+    /// Number of fetches in progress and generation of the tokens missing
+    /// from the cache. The generation is incremented upon invalidation.
+    pending: HashMap<Token, (usize, u64)>,
+}
+
+impl<const VALUE_LENGTH: usize> Lru<VALUE_LENGTH> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            next_stamp: 0,
+            values: HashMap::with_capacity(capacity),
+            order: BTreeMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, token: &Token) -> Option<EncryptedValue<VALUE_LENGTH>> {
+        let (stamp, value) = self.values.get_mut(token)?;
+        self.order.remove(stamp);
+        *stamp = self.next_stamp;
+        self.order.insert(self.next_stamp, *token);
+        self.next_stamp += 1;
+        Some(value.clone())
+    }
+
+    fn put(&mut self, token: Token, value: EncryptedValue<VALUE_LENGTH>) {
+        if 0 == self.capacity {
+            return;
+        }
+        if let Some((stamp, _)) = self.values.insert(token, (self.next_stamp, value)) {
+            self.order.remove(&stamp);
+        }
+        self.order.insert(self.next_stamp, token);
+        self.next_stamp += 1;
+        while self.capacity < self.values.len() {
+            if let Some((_, lru_token)) = self.order.pop_first() {
+                self.values.remove(&lru_token);
+            }
+        }
+    }
+
+    /// Registers a fetch of the given token and returns its generation.
+    fn acquire(&mut self, token: Token) -> u64 {
+        let (n_fetches, generation) = self.pending.entry(token).or_default();
+        *n_fetches += 1;
+        *generation
+    }
+
+    /// Unregisters a fetch of the given token and returns `true` if the
+    /// token has not been invalidated since this fetch was registered.
+    fn release(&mut self, token: &Token, generation: u64) -> bool {
+        let Some((n_fetches, current_generation)) = self.pending.get_mut(token) else {
+            return false;
+        };
+        let is_up_to_date = *current_generation == generation;
+        *n_fetches -= 1;
+        if 0 == *n_fetches {
+            self.pending.remove(token);
+        }
+        is_up_to_date
+    }
+
+    fn invalidate<'a>(&mut self, tokens: impl IntoIterator<Item = &'a Token>) {
+        for token in tokens {
+            if let Some((stamp, _)) = self.values.remove(token) {
+                self.order.remove(&stamp);
+            }
+            if let Some((_, generation)) = self.pending.get_mut(token) {
+                *generation += 1;
+            }
+        }
+    }
+}
+
+/// Database serving fetches from a bounded cache of the most recently fetched
+/// lines.
+///
+/// Lines modified through this database are invalidated before the result of
+/// the modification is returned. A fetch concurrent with such a modification
+/// does not cache the value it read. Modifications performed by other clients of
+/// the wrapped database are not seen until the cached lines are evicted: a
+/// cached Entry Table may therefore return stale search results. Additions are
+/// not impacted since stale entries are rejected by the upsert.
+#[derive(Debug)]
+pub struct CachedDb<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> {
+    db: Db,
+    cache: Mutex<Lru<VALUE_LENGTH>>,
+}
+
+impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> CachedDb<VALUE_LENGTH, Db> {
+    /// Wraps the given database with a cache holding at most `capacity` lines.
+    pub fn new(db: Db, capacity: usize) -> Self {
+        Self {
+            db,
+            cache: Mutex::new(Lru::new(capacity)),
+        }
+    }
+
+    fn invalidate<'a>(&self, tokens: impl IntoIterator<Item = &'a Token>) {
+        self.cache
+            .lock()
+            .expect("could not lock mutex")
+            .invalidate(tokens);
+    }
+}
+
+#[async_trait(?Send)]
+impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> DbInterface<VALUE_LENGTH>
+    for CachedDb<VALUE_LENGTH, Db>
+{
+    type Error = Db::Error;
+
+    async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+        self.db.dump_tokens().await
+    }
+
+    async fn fetch(
+        &self,
+        tokens: Tokens,
+    ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+        let mut res = Vec::with_capacity(tokens.len());
+        let mut missing_tokens = HashMap::new();
+        {
+            let mut cache = self.cache.lock().expect("could not lock mutex");
+            for token in tokens {
+                if let Some(value) = cache.get(&token) {
+                    res.push((token, value));
+                } else {
+                    missing_tokens.insert(token, cache.acquire(token));
+                }
+            }
+        }
+
+        if !missing_tokens.is_empty() {
+            let fetched_lines = self
+                .db
+                .fetch(missing_tokens.keys().copied().collect())
+                .await;
+            let mut cache = self.cache.lock().expect("could not lock mutex");
+            // Values read before a concurrent invalidation may be stale.
+            let up_to_date_tokens = missing_tokens
+                .into_iter()
+                .filter(|(token, generation)| cache.release(token, *generation))
+                .map(|(token, _)| token)
+                .collect::<HashSet<_>>();
+            for (token, value) in fetched_lines? {
+                if up_to_date_tokens.contains(&token) {
+                    cache.put(token, value.clone());
+                }
+                res.push((token, value));
+            }
+        }
+
+        Ok(res.into())
+    }
+
+    async fn upsert(
+        &self,
+        old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+        let tokens = new_values.keys().copied().collect::<Vec<_>>();
+        let res = self.db.upsert(old_values, new_values).await;
+        self.invalidate(&tokens);
+        res
+    }
+
+    async fn insert(
+        &self,
+        values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<(), Self::Error> {
+        let tokens = values.keys().copied().collect::<Vec<_>>();
+        let res = self.db.insert(values).await;
+        self.invalidate(&tokens);
+        res
+    }
+
+    async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+        let res = self.db.delete(tokens.clone()).await;
+        self.invalidate(&*tokens);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmian_crypto_core::{reexport::rand_core::SeedableRng, CsRng};
+    use futures::channel::oneshot;
+
+    use super::*;
+    use crate::edx::{
+        audit::{AuditedDb, AuditedOperation, VecSink},
+        in_memory::{
+            tests::{random_line, VALUE_LENGTH},
+            InMemoryDb,
+        },
+    };
+
+    /// Returns the number of fetches performed on the audited database.
+    fn n_fetches(sink: &VecSink) -> usize {
+        sink.records()
+            .iter()
+            .filter(|record| record.operation == AuditedOperation::Fetch)
+            .count()
+    }
+
+    /// Database whose first fetch waits for a signal after reading the stored
+    /// values.
+    struct GatedDb {
+        db: InMemoryDb<VALUE_LENGTH>,
+        gate: Mutex<Option<oneshot::Receiver<()>>>,
+    }
+
+    #[async_trait(?Send)]
+    impl DbInterface<VALUE_LENGTH> for GatedDb {
+        type Error = <InMemoryDb<VALUE_LENGTH> as DbInterface<VALUE_LENGTH>>::Error;
+
+        async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+            self.db.dump_tokens().await
+        }
+
+        async fn fetch(
+            &self,
+            tokens: Tokens,
+        ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+            let res = self.db.fetch(tokens).await;
+            let gate = self.gate.lock().expect("could not lock mutex").take();
+            if let Some(gate) = gate {
+                gate.await.expect("gate sender dropped");
+            }
+            res
+        }
+
+        async fn upsert(
+            &self,
+            old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+            new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+            self.db.upsert(old_values, new_values).await
+        }
+
+        async fn insert(
+            &self,
+            values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        ) -> Result<(), Self::Error> {
+            self.db.insert(values).await
+        }
+
+        async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+            self.db.delete(tokens).await
+        }
+    }
+
+    crate::db_interface_conformance_tests!(
+        CachedDb<VALUE_LENGTH, InMemoryDb<VALUE_LENGTH>>,
+        CachedDb::new(InMemoryDb::default(), 16)
+    );
+
+    #[actix_rt::test]
+    async fn test_cache() {
+        let mut rng = CsRng::from_entropy();
+        let sink = VecSink::default();
+        let db = CachedDb::new(
+            AuditedDb::new(InMemoryDb::<VALUE_LENGTH>::default(), sink.clone(), true),
+            1,
+        );
+
+        let (token_1, value_1) = random_line(&mut rng);
+        let (token_2, value_2) = random_line(&mut rng);
+        db.insert(TokenToEncryptedValueMap::from_iter([
+            (token_1, value_1.clone()),
+            (token_2, value_2.clone()),
+        ]))
+        .await
+        .unwrap();
+
+        // The second fetch is served from the cache.
+        for _ in 0..2 {
+            let res = db.fetch(Tokens::from_iter([token_1])).await.unwrap();
+            assert_eq!(res.0, vec![(token_1, value_1.clone())]);
+            assert_eq!(1, n_fetches(&sink));
+        }
+
+        // An upsert invalidates the upserted lines.
+        let (_, new_value_1) = random_line(&mut rng);
+        let rejected = db
+            .upsert(
+                TokenToEncryptedValueMap::from_iter([(token_1, value_1)]),
+                TokenToEncryptedValueMap::from_iter([(token_1, new_value_1.clone())]),
+            )
+            .await
+            .unwrap();
+        assert!(rejected.is_empty());
+        let res = db.fetch(Tokens::from_iter([token_1])).await.unwrap();
+        assert_eq!(res.0, vec![(token_1, new_value_1)]);
+        assert_eq!(2, n_fetches(&sink));
+
+        // Fetching another line evicts the least recently used one.
+        let res = db.fetch(Tokens::from_iter([token_2])).await.unwrap();
+        assert_eq!(res.0, vec![(token_2, value_2)]);
+        assert_eq!(3, n_fetches(&sink));
+        db.fetch(Tokens::from_iter([token_1])).await.unwrap();
+        assert_eq!(4, n_fetches(&sink));
+
+        // A deletion invalidates the deleted lines.
+        db.delete(Tokens::from_iter([token_1])).await.unwrap();
+        let res = db.fetch(Tokens::from_iter([token_1])).await.unwrap();
+        assert!(res.is_empty());
+        assert_eq!(5, n_fetches(&sink));
+    }
+
+    #[actix_rt::test]
+    async fn test_concurrent_invalidation() {
+        let mut rng = CsRng::from_entropy();
+        let (sender, receiver) = oneshot::channel();
+        let db = CachedDb::new(
+            GatedDb {
+                db: InMemoryDb::default(),
+                gate: Mutex::new(Some(receiver)),
+            },
+            1,
+        );
+
+        let (token, value) = random_line(&mut rng);
+        let (_, new_value) = random_line(&mut rng);
+        db.insert(TokenToEncryptedValueMap::from_iter([(
+            token,
+            value.clone(),
+        )]))
+        .await
+        .unwrap();
+
+        // The fetch reads the old value, and the upsert completes before the
+        // fetch returns.
+        let (res, ()) = futures::join!(db.fetch(Tokens::from_iter([token])), async {
+            let rejected = db
+                .upsert(
+                    TokenToEncryptedValueMap::from_iter([(token, value.clone())]),
+                    TokenToEncryptedValueMap::from_iter([(token, new_value.clone())]),
+                )
+                .await
+                .unwrap();
+            assert!(rejected.is_empty());
+            sender.send(()).unwrap();
+        });
+        assert_eq!(res.unwrap().0, vec![(token, value)]);
+
+        // The stale value has not been cached.
+        let res = db.fetch(Tokens::from_iter([token])).await.unwrap();
+        assert_eq!(res.0, vec![(token, new_value)]);
+    }
+}
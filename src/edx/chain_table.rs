@@ -12,12 +12,13 @@ use std::{
 
 use async_trait::async_trait;
 use cosmian_crypto_core::{kdf256, reexport::rand_core::CryptoRngCore, SymmetricKey};
+use tracing::{instrument, Span};
 
-use super::structs::Token;
+use super::{line_length, structs::Token};
 use crate::{
     edx::{
         structs::{EdxKey, Seed},
-        DbInterface, DxEnc,
+        DbInterface, DbSize, DxEnc, TokenDump,
     },
     error::Error,
     parameters::{SEED_LENGTH, TOKEN_LENGTH},
@@ -89,15 +90,18 @@ impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> DxEnc<VALUE_LENGT
         .into()
     }
 
+    #[instrument(level = "debug", skip_all, fields(n_tokens = tokens.len(), n_bytes))]
     async fn get(
         &self,
         tokens: HashSet<Token>,
     ) -> Result<Vec<(Token, Self::EncryptedValue)>, Self::Error> {
-        self.0
+        let lines = self
+            .0
             .fetch(tokens.into())
             .await
-            .map_err(Error::DbInterface)
-            .map(Into::into)
+            .map_err(Error::DbInterface)?;
+        Span::current().record("n_bytes", lines.len() * line_length::<VALUE_LENGTH>());
+        Ok(lines.into())
     }
 
     fn resolve(
@@ -125,6 +129,11 @@ impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> DxEnc<VALUE_LENGT
         panic!("The Chain Table does not do any upsert.")
     }
 
+    #[instrument(
+        level = "debug",
+        skip_all,
+        fields(n_tokens = items.len(), n_bytes = items.len() * line_length::<VALUE_LENGTH>())
+    )]
     async fn insert(&self, items: HashMap<Token, Self::EncryptedValue>) -> Result<(), Self::Error> {
         self.0
             .insert(items.into())
@@ -132,6 +141,7 @@ impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> DxEnc<VALUE_LENGT
             .map_err(Error::DbInterface)
     }
 
+    #[instrument(level = "debug", skip_all, fields(n_tokens = items.len()))]
     async fn delete(&self, items: HashSet<Token>) -> Result<(), Self::Error> {
         self.0
             .delete(items.into())
@@ -140,6 +150,34 @@ impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> DxEnc<VALUE_LENGT
     }
 }
 
+#[async_trait(?Send)]
+impl<const VALUE_LENGTH: usize, Edx: DbInterface<VALUE_LENGTH>> TokenDump
+    for ChainTable<VALUE_LENGTH, Edx>
+{
+    type Error = <Self as DxEnc<VALUE_LENGTH>>::Error;
+
+    async fn dump_tokens(&self) -> Result<HashSet<Token>, Self::Error> {
+        self.0
+            .dump_tokens()
+            .await
+            .map_err(Error::DbInterface)
+            .map(Into::into)
+    }
+}
+
+#[async_trait(?Send)]
+impl<
+        const VALUE_LENGTH: usize,
+        Edx: DbInterface<VALUE_LENGTH> + DbSize<Error = <Edx as DbInterface<VALUE_LENGTH>>::Error>,
+    > DbSize for ChainTable<VALUE_LENGTH, Edx>
+{
+    type Error = <Self as DxEnc<VALUE_LENGTH>>::Error;
+
+    async fn len(&self) -> Result<usize, Self::Error> {
+        DbSize::len(&self.0).await.map_err(Error::DbInterface)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
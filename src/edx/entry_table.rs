@@ -11,10 +11,12 @@ use std::{
 
 use async_trait::async_trait;
 use cosmian_crypto_core::{kdf256, reexport::rand_core::CryptoRngCore, SymmetricKey};
+use tracing::{instrument, Span};
 
 use super::{
+    line_length,
     structs::{EdxKey, Seed, Token},
-    TokenDump,
+    DbSize, TokenDump,
 };
 use crate::{
     edx::{DbInterface, DxEnc},
@@ -85,15 +87,18 @@ impl<const VALUE_LENGTH: usize, Edx: DbInterface<VALUE_LENGTH>> DxEnc<VALUE_LENG
         }
     }
 
+    #[instrument(level = "debug", skip_all, fields(n_tokens = tokens.len(), n_bytes))]
     async fn get(
         &self,
         tokens: HashSet<Token>,
     ) -> Result<Vec<(Token, Self::EncryptedValue)>, Self::Error> {
-        self.0
+        let lines = self
+            .0
             .fetch(tokens.into())
             .await
-            .map_err(Self::Error::from)
-            .map(Into::into)
+            .map_err(Self::Error::from)?;
+        Span::current().record("n_bytes", lines.len() * line_length::<VALUE_LENGTH>());
+        Ok(lines.into())
     }
 
     fn resolve(
@@ -104,6 +109,14 @@ impl<const VALUE_LENGTH: usize, Edx: DbInterface<VALUE_LENGTH>> DxEnc<VALUE_LENG
         encrypted_value.decrypt(&key.value).map_err(Error::from)
     }
 
+    #[instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            n_tokens = new_values.len(),
+            n_bytes = new_values.len() * line_length::<VALUE_LENGTH>()
+        )
+    )]
     async fn upsert(
         &self,
         old_values: HashMap<Token, Self::EncryptedValue>,
@@ -116,6 +129,11 @@ impl<const VALUE_LENGTH: usize, Edx: DbInterface<VALUE_LENGTH>> DxEnc<VALUE_LENG
             .map(Into::into)
     }
 
+    #[instrument(
+        level = "debug",
+        skip_all,
+        fields(n_tokens = items.len(), n_bytes = items.len() * line_length::<VALUE_LENGTH>())
+    )]
     async fn insert(&self, items: HashMap<Token, Self::EncryptedValue>) -> Result<(), Self::Error> {
         self.0
             .insert(items.into())
@@ -132,6 +150,7 @@ impl<const VALUE_LENGTH: usize, Edx: DbInterface<VALUE_LENGTH>> DxEnc<VALUE_LENG
         Self::EncryptedValue::encrypt(rng, &key.value, value).map_err(Error::from)
     }
 
+    #[instrument(level = "debug", skip_all, fields(n_tokens = items.len()))]
     async fn delete(&self, items: HashSet<Token>) -> Result<(), Self::Error> {
         self.0
             .delete(items.into())
@@ -155,18 +174,80 @@ impl<const VALUE_LENGTH: usize, Edx: DbInterface<VALUE_LENGTH>> TokenDump
     }
 }
 
+#[async_trait(?Send)]
+impl<
+        const VALUE_LENGTH: usize,
+        Edx: DbInterface<VALUE_LENGTH> + DbSize<Error = <Edx as DbInterface<VALUE_LENGTH>>::Error>,
+    > DbSize for EntryTable<VALUE_LENGTH, Edx>
+{
+    type Error = <Self as DxEnc<VALUE_LENGTH>>::Error;
+
+    async fn len(&self) -> Result<usize, Self::Error> {
+        DbSize::len(&self.0).await.map_err(Error::DbInterface)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use cosmian_crypto_core::{
         reexport::rand_core::{RngCore, SeedableRng},
         CsRng,
     };
+    use futures::executor::block_on;
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
 
     use super::*;
     use crate::edx::in_memory::InMemoryDb;
 
     const VALUE_LENGTH: usize = 32;
 
+    /// Subscriber recording the name and the number of tokens of each span.
+    #[derive(Default)]
+    struct SpanRecorder(Mutex<Vec<(&'static str, Option<u64>)>>);
+
+    /// Visitor reading the `n_tokens` field.
+    struct NTokens(Option<u64>);
+
+    impl Visit for NTokens {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            if field.name() == "n_tokens" {
+                self.0 = Some(value);
+            }
+        }
+
+        fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut n_tokens = NTokens(None);
+            span.record(&mut n_tokens);
+            let mut spans = self.0.lock().unwrap();
+            spans.push((span.metadata().name(), n_tokens.0));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
     #[actix_rt::test]
     async fn test_edx() {
         let mut rng = CsRng::from_entropy();
@@ -203,4 +284,34 @@ mod tests {
         let decrypted_value = table.resolve(&key, ciphertext).unwrap();
         assert_eq!(decrypted_value, value);
     }
+
+    #[test]
+    fn test_spans() {
+        let mut rng = CsRng::from_entropy();
+        let table = EntryTable::setup(InMemoryDb::<VALUE_LENGTH>::default());
+        let seed = table.gen_seed(&mut rng);
+        let key = table.derive_keys(&seed);
+
+        let lines = (0..3)
+            .map(|i| {
+                let token = table.tokenize(&key, &[i], None);
+                let value = table.prepare(&mut rng, &key, [i; VALUE_LENGTH]).unwrap();
+                (token, value)
+            })
+            .collect::<HashMap<_, _>>();
+        let tokens = lines.keys().copied().take(2).collect::<HashSet<_>>();
+
+        let recorder = Arc::new(SpanRecorder::default());
+        tracing::subscriber::with_default(recorder.clone(), || {
+            block_on(async {
+                table.insert(lines).await.unwrap();
+                table.get(tokens).await.unwrap();
+            });
+        });
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![("insert", Some(3)), ("get", Some(2))]
+        );
+    }
 }
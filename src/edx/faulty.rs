@@ -0,0 +1,337 @@
+//! Implements a `DbInterface` adapter injecting failures into the operations
+//! performed on the wrapped database.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use cosmian_crypto_core::{
+    reexport::rand_core::{RngCore, SeedableRng},
+    CsRng,
+};
+
+use super::{
+    audit::AuditedOperation, DbInterface, TokenToEncryptedValueMap, TokenWithEncryptedValueList,
+    Tokens,
+};
+use crate::DbInterfaceErrorTrait;
+
+/// Error returned by a faulty database.
+#[derive(Debug)]
+pub enum FaultyDbError<E> {
+    /// Failure injected in place of the given operation. The wrapped database
+    /// was not called.
+    Injected(AuditedOperation),
+    /// Error returned by the wrapped database.
+    Db(E),
+}
+
+impl<E: Display> Display for FaultyDbError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Injected(operation) => write!(f, "injected {operation:?} failure"),
+            Self::Db(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl<E: std::error::Error> std::error::Error for FaultyDbError<E> {}
+impl<E: DbInterfaceErrorTrait> DbInterfaceErrorTrait for FaultyDbError<E> {}
+
+#[derive(Debug)]
+enum Faults {
+    /// Numbers of the calls to fail for each operation, starting at 1.
+    Scripted {
+        failures: HashSet<(AuditedOperation, usize)>,
+        n_calls: HashMap<AuditedOperation, usize>,
+    },
+    /// Each call fails with the given probability.
+    Random { rng: Box<CsRng>, probability: f64 },
+}
+
+impl Faults {
+    fn next_call_fails(&mut self, operation: AuditedOperation) -> bool {
+        match self {
+            Self::Scripted { failures, n_calls } => {
+                let n = n_calls.entry(operation).or_default();
+                *n += 1;
+                failures.contains(&(operation, *n))
+            }
+            Self::Random { rng, probability } => {
+                f64::from(rng.next_u32()) < *probability * f64::from(u32::MAX)
+            }
+        }
+    }
+}
+
+/// Database failing some of the operations performed on it, in a
+/// reproducible way.
+///
+/// A failed operation returns a [`FaultyDbError::Injected`] error without
+/// calling the wrapped database, so it is never partially applied. Injected
+/// upsert failures are errors, not rejections: they are never confused with
+/// concurrent modifications.
+#[derive(Debug)]
+pub struct FaultyDb<Db> {
+    db: Db,
+    faults: Mutex<Faults>,
+}
+
+impl<Db> FaultyDb<Db> {
+    /// Fails the given calls. Each call is given by its operation and its
+    /// number among the calls to this operation, starting at 1: e.g.
+    /// `(AuditedOperation::Upsert, 3)` fails the third upsert. All other
+    /// calls are forwarded to the wrapped database.
+    pub fn scripted(db: Db, failures: impl IntoIterator<Item = (AuditedOperation, usize)>) -> Self {
+        Self {
+            db,
+            faults: Mutex::new(Faults::Scripted {
+                failures: failures.into_iter().collect(),
+                n_calls: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Fails each call with the given probability. The same seed always
+    /// fails the same sequence of calls.
+    pub fn random(db: Db, seed: [u8; 32], probability: f64) -> Self {
+        Self {
+            db,
+            faults: Mutex::new(Faults::Random {
+                rng: Box::new(CsRng::from_seed(seed)),
+                probability,
+            }),
+        }
+    }
+
+    fn inject<E>(&self, operation: AuditedOperation) -> Result<(), FaultyDbError<E>> {
+        if self
+            .faults
+            .lock()
+            .expect("could not lock mutex")
+            .next_call_fails(operation)
+        {
+            Err(FaultyDbError::Injected(operation))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> DbInterface<VALUE_LENGTH>
+    for FaultyDb<Db>
+{
+    type Error = FaultyDbError<Db::Error>;
+
+    async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+        self.inject(AuditedOperation::DumpTokens)?;
+        self.db.dump_tokens().await.map_err(FaultyDbError::Db)
+    }
+
+    async fn fetch(
+        &self,
+        tokens: Tokens,
+    ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+        self.inject(AuditedOperation::Fetch)?;
+        self.db.fetch(tokens).await.map_err(FaultyDbError::Db)
+    }
+
+    async fn upsert(
+        &self,
+        old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+        self.inject(AuditedOperation::Upsert)?;
+        self.db
+            .upsert(old_values, new_values)
+            .await
+            .map_err(FaultyDbError::Db)
+    }
+
+    async fn insert(
+        &self,
+        values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<(), Self::Error> {
+        self.inject(AuditedOperation::Insert)?;
+        self.db.insert(values).await.map_err(FaultyDbError::Db)
+    }
+
+    async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+        self.inject(AuditedOperation::Delete)?;
+        self.db.delete(tokens).await.map_err(FaultyDbError::Db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        edx::{
+            chain_table::ChainTable,
+            entry_table::EntryTable,
+            in_memory::{tests::VALUE_LENGTH, InMemoryDb, InMemoryDbError},
+        },
+        Data, DxEnc, Error, Findex, Index, IndexedValue, IndexedValueToKeywordsMap, Keyword,
+        Keywords, Label,
+    };
+
+    crate::db_interface_conformance_tests!(
+        FaultyDb<InMemoryDb<VALUE_LENGTH>>,
+        FaultyDb::scripted(InMemoryDb::default(), [])
+    );
+
+    #[actix_rt::test]
+    async fn test_random_faults() {
+        let n_failures = |seed| async move {
+            let db = FaultyDb::random(InMemoryDb::<VALUE_LENGTH>::default(), seed, 0.5);
+            let mut n = 0;
+            for _ in 0..100 {
+                if db.fetch(Tokens::from(HashSet::new())).await.is_err() {
+                    n += 1;
+                }
+            }
+            n
+        };
+        let n = n_failures([1; 32]).await;
+        assert!((25..75).contains(&n));
+        assert_eq!(n, n_failures([1; 32]).await);
+    }
+
+    #[actix_rt::test]
+    async fn test_failed_chain_insert() {
+        let entry_table = InMemoryDb::default();
+        let chain_table = InMemoryDb::default();
+        let findex = Findex::new(
+            EntryTable::setup(FaultyDb::scripted(entry_table.clone(), [])),
+            ChainTable::setup(FaultyDb::scripted(
+                chain_table.clone(),
+                [(AuditedOperation::Insert, 1)],
+            )),
+        );
+        let key = findex.keygen();
+        let label = Label::from("label");
+        let keyword = Keyword::from("keyword");
+        let associations = |location: &str| {
+            IndexedValueToKeywordsMap::from_iter([(
+                IndexedValue::Data(Data::from(location)),
+                Keywords::from_iter([keyword.clone()]),
+            )])
+        };
+
+        let res = findex.add(&key, &label, associations("location 1")).await;
+        assert!(
+            matches!(
+                res,
+                Err(Error::DbInterface(FaultyDbError::Injected(
+                    AuditedOperation::Insert
+                )))
+            ),
+            "{res:?}"
+        );
+        assert_eq!(1, entry_table.len());
+        assert_eq!(0, chain_table.len());
+
+        // The database error is exposed as the source of the Findex error.
+        let err = res.unwrap_err();
+        let source = std::error::Error::source(&err)
+            .and_then(|err| err.downcast_ref::<FaultyDbError<InMemoryDbError>>());
+        assert!(
+            matches!(
+                source,
+                Some(FaultyDbError::Injected(AuditedOperation::Insert))
+            ),
+            "{source:?}"
+        );
+
+        // The failed addition was rolled back: the next addition to the same
+        // keyword succeeds and only its value is found.
+        findex
+            .add(&key, &label, associations("location 2"))
+            .await
+            .unwrap();
+        let res = findex
+            .search(
+                &key,
+                &label,
+                Keywords::from_iter([keyword.clone()]),
+                &|_| async { Ok(false) },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            res.get(&keyword),
+            Some(&HashSet::from_iter([Data::from("location 2")]))
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_failed_dump() {
+        let findex = Findex::new(
+            EntryTable::setup(FaultyDb::scripted(
+                InMemoryDb::default(),
+                [(AuditedOperation::DumpTokens, 1)],
+            )),
+            ChainTable::setup(FaultyDb::scripted(InMemoryDb::default(), [])),
+        );
+        let key = findex.keygen();
+        let label = Label::from("label");
+        let keyword = Keyword::from("keyword");
+        findex
+            .add(
+                &key,
+                &label,
+                IndexedValueToKeywordsMap::from_iter([(
+                    IndexedValue::Data(Data::from("location")),
+                    Keywords::from_iter([keyword.clone()]),
+                )]),
+            )
+            .await
+            .unwrap();
+
+        // The compaction starts by dumping the Entry Table.
+        let new_key = findex.keygen();
+        let new_label = Label::from("new label");
+        let res = findex
+            .compact(&key, &new_key, &label, &new_label, 1f64, &|data| async {
+                Ok(data)
+            })
+            .await;
+        assert!(
+            matches!(
+                res,
+                Err(Error::DbInterface(FaultyDbError::Injected(
+                    AuditedOperation::DumpTokens
+                )))
+            ),
+            "{res:?}"
+        );
+
+        // The index was not modified.
+        let res = findex
+            .search(
+                &key,
+                &label,
+                Keywords::from_iter([keyword.clone()]),
+                &|_| async { Ok(false) },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            res.get(&keyword),
+            Some(&HashSet::from_iter([Data::from("location")]))
+        );
+
+        findex
+            .compact(&key, &new_key, &label, &new_label, 1f64, &|data| async {
+                Ok(data)
+            })
+            .await
+            .unwrap();
+    }
+}
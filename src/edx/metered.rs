@@ -0,0 +1,220 @@
+//! Implements a `DbInterface` adapter counting the bytes moved to and from
+//! the wrapped database.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+
+use super::{
+    line_length, DbInterface, TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens,
+};
+
+/// Amount of data moved to and from a metered database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoStats {
+    /// Size of the lines returned by fetches and rejected upserts.
+    pub bytes_read: usize,
+    /// Size of the lines written by upserts and inserts. Lines rejected by an
+    /// upsert and lines sent by a failed operation are not counted.
+    pub bytes_written: usize,
+    /// Number of operations performed, including the failed ones.
+    pub ops: usize,
+}
+
+/// Database counting the bytes moved by the operations performed on the
+/// wrapped database.
+///
+/// Lines are counted with their token, using their serialized size. Tokens
+/// sent by fetches and deletions are not counted as moved bytes.
+#[derive(Debug)]
+pub struct MeteredDb<Db> {
+    db: Db,
+    bytes_read: AtomicUsize,
+    bytes_written: AtomicUsize,
+    ops: AtomicUsize,
+}
+
+impl<Db> MeteredDb<Db> {
+    pub const fn new(db: Db) -> Self {
+        Self {
+            db,
+            bytes_read: AtomicUsize::new(0),
+            bytes_written: AtomicUsize::new(0),
+            ops: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the amount of data moved since the instantiation of this
+    /// database.
+    pub fn io_stats(&self) -> IoStats {
+        IoStats {
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            ops: self.ops.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record<const VALUE_LENGTH: usize>(&self, n_lines_read: usize, n_lines_written: usize) {
+        self.bytes_read.fetch_add(
+            n_lines_read * line_length::<VALUE_LENGTH>(),
+            Ordering::Relaxed,
+        );
+        self.bytes_written.fetch_add(
+            n_lines_written * line_length::<VALUE_LENGTH>(),
+            Ordering::Relaxed,
+        );
+        self.ops.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[async_trait(?Send)]
+impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> DbInterface<VALUE_LENGTH>
+    for MeteredDb<Db>
+{
+    type Error = Db::Error;
+
+    async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+        self.db.dump_tokens().await
+    }
+
+    async fn fetch(
+        &self,
+        tokens: Tokens,
+    ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+        let res = self.db.fetch(tokens).await;
+        self.record::<VALUE_LENGTH>(res.as_ref().map_or(0, |lines| lines.len()), 0);
+        res
+    }
+
+    async fn upsert(
+        &self,
+        old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+        let n_lines = new_values.len();
+        let res = self.db.upsert(old_values, new_values).await;
+        let (n_lines_read, n_lines_written) = res.as_ref().map_or((0, 0), |rejected| {
+            (rejected.len(), n_lines - rejected.len())
+        });
+        self.record::<VALUE_LENGTH>(n_lines_read, n_lines_written);
+        res
+    }
+
+    async fn insert(
+        &self,
+        values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<(), Self::Error> {
+        let n_lines = values.len();
+        let res = self.db.insert(values).await;
+        self.record::<VALUE_LENGTH>(0, if res.is_ok() { n_lines } else { 0 });
+        res
+    }
+
+    async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+        let res = self.db.delete(tokens).await;
+        self.record::<VALUE_LENGTH>(0, 0);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmian_crypto_core::{reexport::rand_core::SeedableRng, CsRng};
+
+    use super::*;
+    use crate::edx::in_memory::{
+        tests::{random_line, VALUE_LENGTH},
+        InMemoryDb,
+    };
+
+    crate::db_interface_conformance_tests!(
+        MeteredDb<InMemoryDb<VALUE_LENGTH>>,
+        MeteredDb::new(InMemoryDb::default())
+    );
+
+    #[actix_rt::test]
+    async fn test_io_stats() {
+        let mut rng = CsRng::from_entropy();
+        let db = MeteredDb::new(InMemoryDb::<VALUE_LENGTH>::default());
+        let line_length = line_length::<VALUE_LENGTH>();
+
+        let n_inserts = 3;
+        let n_lines = 10;
+        let mut tokens = Tokens::from_iter([]);
+        for _ in 0..n_inserts {
+            let lines = (0..n_lines)
+                .map(|_| random_line(&mut rng))
+                .collect::<TokenToEncryptedValueMap<VALUE_LENGTH>>();
+            tokens.0.extend(lines.keys());
+            db.insert(lines).await.unwrap();
+        }
+        assert_eq!(
+            db.io_stats(),
+            IoStats {
+                bytes_read: 0,
+                bytes_written: n_inserts * n_lines * line_length,
+                ops: n_inserts,
+            }
+        );
+
+        db.fetch(tokens).await.unwrap();
+        assert_eq!(
+            db.io_stats(),
+            IoStats {
+                bytes_read: n_inserts * n_lines * line_length,
+                bytes_written: n_inserts * n_lines * line_length,
+                ops: n_inserts + 1,
+            }
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_failed_writes() {
+        let mut rng = CsRng::from_entropy();
+        let db = MeteredDb::new(InMemoryDb::<VALUE_LENGTH>::default());
+        let line_length = line_length::<VALUE_LENGTH>();
+
+        let (token, value) = random_line(&mut rng);
+        let (_, new_value) = random_line(&mut rng);
+        db.insert(TokenToEncryptedValueMap::from_iter([(
+            token,
+            value.clone(),
+        )]))
+        .await
+        .unwrap();
+
+        // Inserting a stored token fails.
+        db.insert(TokenToEncryptedValueMap::from_iter([(
+            token,
+            new_value.clone(),
+        )]))
+        .await
+        .unwrap_err();
+        assert_eq!(db.io_stats().bytes_written, line_length);
+
+        // The rejected line is read but not written.
+        let (other_token, other_value) = random_line(&mut rng);
+        let rejected = db
+            .upsert(
+                TokenToEncryptedValueMap::default(),
+                TokenToEncryptedValueMap::from_iter([
+                    (token, new_value),
+                    (other_token, other_value),
+                ]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            rejected,
+            TokenToEncryptedValueMap::from_iter([(token, value)])
+        );
+        assert_eq!(
+            db.io_stats(),
+            IoStats {
+                bytes_read: line_length,
+                bytes_written: 2 * line_length,
+                ops: 3,
+            }
+        );
+    }
+}
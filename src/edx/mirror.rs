@@ -0,0 +1,242 @@
+//! Implements a `DbInterface` adapter replaying the modifications of a
+//! primary database onto a secondary one.
+
+use async_trait::async_trait;
+use tracing::warn;
+
+use super::{DbInterface, TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens};
+
+/// Database mirroring the modifications performed on a primary database
+/// onto a secondary database, e.g. to migrate an index to a new backend
+/// without interrupting it.
+///
+/// All operations are performed on the primary database first and their
+/// result is returned as is. Successful modifications are then replayed on
+/// the secondary database: the lines accepted by an upsert are upserted with
+/// the same old values. Failures and rejections of the secondary database are
+/// logged but not returned.
+///
+/// Lines stored before the mirroring started must be copied separately, and
+/// a failed replay leaves the secondary database behind until the affected
+/// lines are written again.
+#[derive(Debug)]
+pub struct MirroredDb<Primary, Secondary> {
+    primary: Primary,
+    secondary: Secondary,
+}
+
+impl<Primary, Secondary> MirroredDb<Primary, Secondary> {
+    pub const fn new(primary: Primary, secondary: Secondary) -> Self {
+        Self { primary, secondary }
+    }
+}
+
+#[async_trait(?Send)]
+impl<
+        const VALUE_LENGTH: usize,
+        Primary: DbInterface<VALUE_LENGTH>,
+        Secondary: DbInterface<VALUE_LENGTH>,
+    > DbInterface<VALUE_LENGTH> for MirroredDb<Primary, Secondary>
+{
+    type Error = Primary::Error;
+
+    async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+        self.primary.dump_tokens().await
+    }
+
+    async fn fetch(
+        &self,
+        tokens: Tokens,
+    ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+        self.primary.fetch(tokens).await
+    }
+
+    async fn upsert(
+        &self,
+        old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+        let mut accepted_old_values = old_values.clone();
+        let mut accepted_values = new_values.clone();
+        let rejected_values = self.primary.upsert(old_values, new_values).await?;
+        accepted_old_values.retain(|token, _| !rejected_values.contains_key(token));
+        accepted_values.retain(|token, _| !rejected_values.contains_key(token));
+        if !accepted_values.is_empty() {
+            match self
+                .secondary
+                .upsert(accepted_old_values, accepted_values)
+                .await
+            {
+                Ok(diverging_values) if !diverging_values.is_empty() => warn!(
+                    "mirrored upsert rejected {} lines accepted by the primary",
+                    diverging_values.len()
+                ),
+                Ok(_) => {}
+                Err(err) => warn!("mirrored upsert failed: {err}"),
+            }
+        }
+        Ok(rejected_values)
+    }
+
+    async fn insert(
+        &self,
+        values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<(), Self::Error> {
+        self.primary.insert(values.clone()).await?;
+        if let Err(err) = self.secondary.insert(values).await {
+            warn!("mirrored insert failed: {err}");
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+        self.primary.delete(tokens.clone()).await?;
+        if let Err(err) = self.secondary.delete(tokens).await {
+            warn!("mirrored delete failed: {err}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        edx::{
+            audit::AuditedOperation,
+            chain_table::ChainTable,
+            entry_table::EntryTable,
+            faulty::FaultyDb,
+            in_memory::{tests::VALUE_LENGTH, InMemoryDb},
+        },
+        Data, DxEnc, Findex, Index, IndexedValue, IndexedValueToKeywordsMap, Keyword, Keywords,
+        Label,
+    };
+
+    crate::db_interface_conformance_tests!(
+        MirroredDb<InMemoryDb<VALUE_LENGTH>, InMemoryDb<VALUE_LENGTH>>,
+        MirroredDb::new(InMemoryDb::default(), InMemoryDb::default())
+    );
+
+    #[actix_rt::test]
+    async fn test_mirroring() {
+        let primary_et = InMemoryDb::default();
+        let primary_ct = InMemoryDb::default();
+        let secondary_et = InMemoryDb::default();
+        let secondary_ct = InMemoryDb::default();
+        let findex = Findex::new(
+            EntryTable::setup(MirroredDb::new(primary_et.clone(), secondary_et.clone())),
+            ChainTable::setup(MirroredDb::new(primary_ct.clone(), secondary_ct.clone())),
+        );
+        let key = findex.keygen();
+        let label = Label::from("label");
+        let associations = |location: &str| {
+            IndexedValueToKeywordsMap::from_iter([(
+                IndexedValue::Data(Data::from(location)),
+                Keywords::from_iter([Keyword::from("keyword"), Keyword::from(location)]),
+            )])
+        };
+
+        for i in 0..10 {
+            findex
+                .add(&key, &label, associations(&format!("location {i}")))
+                .await
+                .unwrap();
+        }
+        findex
+            .delete(&key, &label, associations("location 0"))
+            .await
+            .unwrap();
+        let new_key = findex.keygen();
+        findex
+            .compact(&key, &new_key, &label, &label, 1.0, &|data| async {
+                Ok(data)
+            })
+            .await
+            .unwrap();
+
+        assert!(!primary_et.is_empty());
+        assert_eq!(*primary_et.lock().unwrap(), *secondary_et.lock().unwrap());
+        assert_eq!(*primary_ct.lock().unwrap(), *secondary_ct.lock().unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn test_mirrored_updates() {
+        let primary_et = InMemoryDb::default();
+        let primary_ct = InMemoryDb::default();
+        let secondary_et = InMemoryDb::default();
+        let secondary_ct = InMemoryDb::default();
+        let findex = Findex::new(
+            EntryTable::setup(MirroredDb::new(primary_et.clone(), secondary_et.clone())),
+            ChainTable::setup(MirroredDb::new(primary_ct.clone(), secondary_ct.clone())),
+        );
+        let key = findex.keygen();
+        let label = Label::from("label");
+
+        // Each addition updates the Entry Table line of the keyword.
+        for i in 0..5 {
+            findex
+                .add(
+                    &key,
+                    &label,
+                    IndexedValueToKeywordsMap::from_iter([(
+                        IndexedValue::Data(Data::from(format!("location {i}").as_str())),
+                        Keywords::from_iter([Keyword::from("keyword")]),
+                    )]),
+                )
+                .await
+                .unwrap();
+            assert_eq!(1, primary_et.len());
+            assert_eq!(*primary_et.lock().unwrap(), *secondary_et.lock().unwrap());
+            assert_eq!(*primary_ct.lock().unwrap(), *secondary_ct.lock().unwrap());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_secondary_failure() {
+        let primary = InMemoryDb::default();
+        let secondary = InMemoryDb::default();
+        let db = MirroredDb::new(
+            primary.clone(),
+            FaultyDb::scripted(secondary.clone(), [(AuditedOperation::Insert, 1)]),
+        );
+        let findex = Findex::new(
+            EntryTable::setup(InMemoryDb::default()),
+            ChainTable::setup(db),
+        );
+        let key = findex.keygen();
+        let label = Label::from("label");
+        let keyword = Keyword::from("keyword");
+
+        // The failure of the secondary database is not returned.
+        findex
+            .add(
+                &key,
+                &label,
+                IndexedValueToKeywordsMap::from_iter([(
+                    IndexedValue::Data(Data::from("location")),
+                    Keywords::from_iter([keyword.clone()]),
+                )]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(1, primary.len());
+        assert!(secondary.is_empty());
+
+        let res = findex
+            .search(
+                &key,
+                &label,
+                Keywords::from_iter([keyword.clone()]),
+                &|_| async { Ok(false) },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            res.get(&keyword),
+            Some(&HashSet::from_iter([Data::from("location")]))
+        );
+    }
+}
@@ -10,8 +10,26 @@ use async_trait::async_trait;
 use cosmian_crypto_core::reexport::rand_core::CryptoRngCore;
 use zeroize::ZeroizeOnDrop;
 
+#[cfg(any(test, feature = "append_log"))]
+pub mod append_log;
+#[cfg(any(test, feature = "audit"))]
+pub mod audit;
+#[cfg(any(test, feature = "test_utils"))]
+pub mod bounded;
+#[cfg(any(test, feature = "cache"))]
+pub mod cache;
 pub mod chain_table;
 pub mod entry_table;
+#[cfg(any(test, feature = "test_utils"))]
+pub mod faulty;
+#[cfg(any(test, feature = "metered"))]
+pub mod metered;
+#[cfg(any(test, feature = "mirror"))]
+pub mod mirror;
+#[cfg(any(test, feature = "replica"))]
+pub mod replica;
+#[cfg(any(test, feature = "shard"))]
+pub mod shard;
 mod structs;
 
 pub use structs::{
@@ -20,6 +38,11 @@ pub use structs::{
 
 use crate::{DbInterfaceErrorTrait, Label};
 
+/// Returns the size in bytes of a line storing a value of the given length.
+pub(crate) const fn line_length<const VALUE_LENGTH: usize>() -> usize {
+    Token::LENGTH + EncryptedValue::<VALUE_LENGTH>::LENGTH
+}
+
 #[async_trait(?Send)]
 pub trait TokenDump {
     type Error;
@@ -27,6 +50,20 @@ pub trait TokenDump {
     async fn dump_tokens(&self) -> Result<HashSet<Token>, Self::Error>;
 }
 
+/// Counts the lines stored in a database without reading them.
+#[async_trait(?Send)]
+pub trait DbSize {
+    type Error;
+
+    /// Returns the number of lines stored.
+    async fn len(&self) -> Result<usize, Self::Error>;
+
+    /// Returns `true` if no line is stored.
+    async fn is_empty(&self) -> Result<bool, Self::Error> {
+        Ok(0 == self.len().await?)
+    }
+}
+
 #[async_trait(?Send)]
 pub trait DxEnc<const VALUE_LENGTH: usize> {
     /// Seed used to derive the key.
@@ -166,10 +203,14 @@ pub mod in_memory {
     use async_trait::async_trait;
     use cosmian_crypto_core::CryptoCoreError;
     #[cfg(feature = "in_memory")]
-    use cosmian_crypto_core::{bytes_ser_de::Serializable, Nonce};
+    use cosmian_crypto_core::{
+        bytes_ser_de::{to_leb128_len, Serializable},
+        Nonce,
+    };
 
     use super::{
-        DbInterface, Token, TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens,
+        line_length, DbInterface, DbSize, Token, TokenToEncryptedValueMap,
+        TokenWithEncryptedValueList, Tokens,
     };
     #[cfg(feature = "in_memory")]
     use crate::parameters::{MAC_LENGTH, NONCE_LENGTH};
@@ -186,14 +227,19 @@ pub mod in_memory {
 
     impl Display for InMemoryDbError {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            write!(f, "callback error")
+            write!(f, "callback error: {}", self.0)
         }
     }
 
     impl std::error::Error for InMemoryDbError {}
     impl DbInterfaceErrorTrait for InMemoryDbError {}
 
-    #[derive(Debug)]
+    /// In-memory database.
+    ///
+    /// Clones share the same underlying storage: modifications performed
+    /// through a clone, including `flush` and `load`, are visible to all
+    /// other clones.
+    #[derive(Debug, Clone)]
     pub struct InMemoryDb<const VALUE_LENGTH: usize>(
         Arc<Mutex<TokenToEncryptedValueMap<VALUE_LENGTH>>>,
     );
@@ -225,7 +271,7 @@ pub mod in_memory {
 
         #[must_use]
         pub fn size(&self) -> usize {
-            self.len() * (Token::LENGTH + EncryptedValue::<VALUE_LENGTH>::LENGTH)
+            self.len() * line_length::<VALUE_LENGTH>()
         }
 
         pub fn flush(&mut self) {
@@ -235,6 +281,24 @@ pub mod in_memory {
         pub fn load(&mut self, table: TokenToEncryptedValueMap<VALUE_LENGTH>) {
             *self.lock().expect("could not lock mutex") = table;
         }
+
+        /// Creates a database storing the given lines. Later lines overwrite
+        /// earlier lines with the same token.
+        pub fn from_pairs(
+            pairs: impl IntoIterator<Item = (Token, EncryptedValue<VALUE_LENGTH>)>,
+        ) -> Self {
+            Self(Arc::new(Mutex::new(pairs.into_iter().collect())))
+        }
+
+        /// Returns a copy of the lines stored, in no particular order.
+        #[must_use]
+        pub fn pairs(&self) -> Vec<(Token, EncryptedValue<VALUE_LENGTH>)> {
+            self.lock()
+                .expect("could not lock mutex")
+                .iter()
+                .map(|(token, value)| (*token, value.clone()))
+                .collect()
+        }
     }
 
     #[cfg(feature = "in_memory")]
@@ -242,8 +306,11 @@ pub mod in_memory {
         type Error = InMemoryDbError;
 
         fn length(&self) -> usize {
-            (self.lock().expect("could not lock mutex").deref()).len()
-                * (Token::LENGTH + NONCE_LENGTH + MAC_LENGTH + VALUE_LENGTH)
+            let n = (self.lock().expect("could not lock mutex").deref()).len();
+            to_leb128_len(n)
+                + n * (Token::LENGTH
+                    + to_leb128_len(EncryptedValue::<VALUE_LENGTH>::LENGTH)
+                    + EncryptedValue::<VALUE_LENGTH>::LENGTH)
         }
 
         fn write(
@@ -254,6 +321,8 @@ pub mod in_memory {
             let mut n = ser.write_leb128_u64(table.len() as u64)?;
             for (k, v) in table.iter() {
                 n += ser.write_array(k)?;
+                // Values are written as a vector, along with their size.
+                n += ser.write_leb128_u64(EncryptedValue::<VALUE_LENGTH>::LENGTH as u64)?;
                 n += ser.write_array(&v.nonce.0)?;
                 n += ser.write_array(&v.ciphertext)?;
                 n += ser.write_array(&v.tag)?;
@@ -268,8 +337,8 @@ pub mod in_memory {
             let mut table = HashMap::with_capacity(n);
             for _ in 0..n {
                 let k = de.read_array::<{ Token::LENGTH }>()?;
-                // previous version used to write the size of the value.
-                let _ = de.read_leb128_u64();
+                // The size of the value is constant.
+                let _ = de.read_leb128_u64()?;
                 let nonce = Nonce::from(de.read_array::<NONCE_LENGTH>()?);
                 let ciphertext = de.read_array::<VALUE_LENGTH>()?;
                 let tag = de.read_array::<MAC_LENGTH>()?;
@@ -289,6 +358,15 @@ pub mod in_memory {
         }
     }
 
+    #[async_trait(?Send)]
+    impl<const VALUE_LENGTH: usize> DbSize for InMemoryDb<VALUE_LENGTH> {
+        type Error = InMemoryDbError;
+
+        async fn len(&self) -> Result<usize, Self::Error> {
+            Ok(self.lock().expect("could not lock mutex").len())
+        }
+    }
+
     #[async_trait(?Send)]
     impl<const VALUE_LENGTH: usize> DbInterface<VALUE_LENGTH> for InMemoryDb<VALUE_LENGTH> {
         type Error = InMemoryDbError;
@@ -386,4 +464,110 @@ pub mod in_memory {
             Ok(())
         }
     }
+
+    #[cfg(test)]
+    pub(crate) mod tests {
+        use cosmian_crypto_core::{
+            reexport::rand_core::{RngCore, SeedableRng},
+            CsRng, Nonce,
+        };
+
+        use super::*;
+        use crate::parameters::{MAC_LENGTH, NONCE_LENGTH};
+
+        pub(crate) const VALUE_LENGTH: usize = 32;
+
+        /// Generates a random token along with a random value.
+        pub(crate) fn random_line(rng: &mut CsRng) -> (Token, EncryptedValue<VALUE_LENGTH>) {
+            let mut token = [0; Token::LENGTH];
+            rng.fill_bytes(&mut token);
+            let mut value = EncryptedValue {
+                ciphertext: [0; VALUE_LENGTH],
+                tag: [0; MAC_LENGTH],
+                nonce: Nonce::from([0; NONCE_LENGTH]),
+            };
+            rng.fill_bytes(&mut value.ciphertext);
+            (Token::from(token), value)
+        }
+
+        crate::db_interface_conformance_tests!(InMemoryDb<VALUE_LENGTH>, InMemoryDb::default());
+
+        #[actix_rt::test]
+        async fn test_from_pairs() {
+            let mut rng = CsRng::from_entropy();
+            let lines = (0..3).map(|_| random_line(&mut rng)).collect::<Vec<_>>();
+            let db = InMemoryDb::from_pairs(lines.clone());
+
+            assert_eq!(
+                db.pairs().into_iter().collect::<HashMap<_, _>>(),
+                lines.iter().cloned().collect::<HashMap<_, _>>()
+            );
+
+            // Absent tokens are not returned.
+            let (absent_token, _) = random_line(&mut rng);
+            let res = db
+                .fetch(Tokens::from_iter([lines[0].0, absent_token]))
+                .await
+                .unwrap();
+            assert_eq!(res.0, vec![lines[0].clone()]);
+        }
+
+        #[actix_rt::test]
+        async fn test_size() {
+            let mut rng = CsRng::from_entropy();
+            let db = InMemoryDb::<VALUE_LENGTH>::default();
+            assert!(DbSize::is_empty(&db).await.unwrap());
+
+            let (token, old_value) = random_line(&mut rng);
+            let res = db
+                .upsert(
+                    TokenToEncryptedValueMap::default(),
+                    TokenToEncryptedValueMap::from_iter([(token, old_value.clone())]),
+                )
+                .await
+                .unwrap();
+            assert!(res.is_empty());
+            assert_eq!(DbSize::len(&db).await.unwrap(), 1);
+
+            // Updating a line does not change the size.
+            let (_, new_value) = random_line(&mut rng);
+            db.upsert(
+                TokenToEncryptedValueMap::from_iter([(token, old_value)]),
+                TokenToEncryptedValueMap::from_iter([(token, new_value)]),
+            )
+            .await
+            .unwrap();
+            assert_eq!(DbSize::len(&db).await.unwrap(), 1);
+
+            let lines = (0..10).map(|_| random_line(&mut rng)).collect::<Vec<_>>();
+            db.upsert(
+                TokenToEncryptedValueMap::default(),
+                lines.into_iter().collect(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(DbSize::len(&db).await.unwrap(), 11);
+            assert!(!DbSize::is_empty(&db).await.unwrap());
+        }
+
+        #[actix_rt::test]
+        async fn test_clone() {
+            let mut rng = CsRng::from_entropy();
+            let db = InMemoryDb::<VALUE_LENGTH>::default();
+
+            let (token, value) = random_line(&mut rng);
+            let clone = db.clone();
+            actix_rt::spawn(async move {
+                clone
+                    .insert(TokenToEncryptedValueMap::from_iter([(token, value)]))
+                    .await
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+            let res = db.fetch(Tokens::from_iter([token])).await.unwrap();
+            assert_eq!(1, res.len());
+        }
+    }
 }
@@ -0,0 +1,157 @@
+//! Implements a `DbInterface` adapter reading from replicas of the database
+//! it writes to.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+
+use super::{DbInterface, TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens};
+
+/// Database sending the modifications to a primary database and spreading
+/// the fetches among its replicas in a round-robin fashion.
+///
+/// Replicas may lag behind the primary: a fetch following a modification may
+/// not see it. This is tolerated by Findex additions since a stale Entry Table
+/// value is rejected by the conditional upsert performed on the primary,
+/// which returns the current value. However, searches may miss the latest
+/// additions and deletions, and a chain may be seen partially written.
+///
+/// Token dumps are served by the primary, but compactions also read the
+/// entries and links they rewrite through fetches, and then delete the old
+/// lines: lines written after the snapshot of a lagging replica would be
+/// deleted without having been read. `compact`, `compact_keyword`, `rebuild`
+/// and `merge` must therefore be run on an index whose tables read from the
+/// primary, e.g. built on `ReplicatedDb::new(primary, Vec::new())`.
+#[derive(Debug)]
+pub struct ReplicatedDb<Primary, Replica> {
+    primary: Primary,
+    replicas: Vec<Replica>,
+    next_replica: AtomicUsize,
+}
+
+impl<Primary, Replica> ReplicatedDb<Primary, Replica> {
+    /// Fetches are sent to the primary database if no replica is given.
+    pub const fn new(primary: Primary, replicas: Vec<Replica>) -> Self {
+        Self {
+            primary,
+            replicas,
+            next_replica: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<
+        const VALUE_LENGTH: usize,
+        Primary: DbInterface<VALUE_LENGTH>,
+        Replica: DbInterface<VALUE_LENGTH, Error = Primary::Error>,
+    > DbInterface<VALUE_LENGTH> for ReplicatedDb<Primary, Replica>
+{
+    type Error = Primary::Error;
+
+    async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+        self.primary.dump_tokens().await
+    }
+
+    async fn fetch(
+        &self,
+        tokens: Tokens,
+    ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+        if self.replicas.is_empty() {
+            return self.primary.fetch(tokens).await;
+        }
+        let i = self.next_replica.fetch_add(1, Ordering::Relaxed) % self.replicas.len();
+        self.replicas[i].fetch(tokens).await
+    }
+
+    async fn upsert(
+        &self,
+        old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+        self.primary.upsert(old_values, new_values).await
+    }
+
+    async fn insert(
+        &self,
+        values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<(), Self::Error> {
+        self.primary.insert(values).await
+    }
+
+    async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+        self.primary.delete(tokens).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmian_crypto_core::{reexport::rand_core::SeedableRng, CsRng};
+
+    use super::*;
+    use crate::edx::{
+        audit::{AuditedDb, AuditedOperation, VecSink},
+        in_memory::{
+            tests::{random_line, VALUE_LENGTH},
+            InMemoryDb,
+        },
+    };
+
+    crate::db_interface_conformance_tests!(
+        ReplicatedDb<InMemoryDb<VALUE_LENGTH>, InMemoryDb<VALUE_LENGTH>>,
+        {
+            // Replicas sharing the primary storage are always up to date.
+            let db = InMemoryDb::default();
+            ReplicatedDb::new(db.clone(), vec![db])
+        }
+    );
+
+    #[actix_rt::test]
+    async fn test_replication() {
+        let mut rng = CsRng::from_entropy();
+        let primary = InMemoryDb::<VALUE_LENGTH>::default();
+        let replicas = [InMemoryDb::default(), InMemoryDb::default()];
+        let sinks = [VecSink::default(), VecSink::default()];
+        let db = ReplicatedDb::new(
+            primary.clone(),
+            replicas
+                .iter()
+                .zip(&sinks)
+                .map(|(replica, sink)| AuditedDb::new(replica.clone(), sink.clone(), true))
+                .collect(),
+        );
+
+        let (token, value) = random_line(&mut rng);
+        db.insert(TokenToEncryptedValueMap::from_iter([(
+            token,
+            value.clone(),
+        )]))
+        .await
+        .unwrap();
+        assert_eq!(1, primary.len());
+
+        // The replicas are not synchronized yet.
+        assert!(db
+            .fetch(Tokens::from_iter([token]))
+            .await
+            .unwrap()
+            .is_empty());
+
+        for replica in &replicas {
+            *replica.lock().unwrap() = primary.lock().unwrap().clone();
+        }
+        for _ in 0..3 {
+            let res = db.fetch(Tokens::from_iter([token])).await.unwrap();
+            assert_eq!(res.0, vec![(token, value.clone())]);
+        }
+
+        // Fetches are evenly spread among the replicas.
+        for sink in &sinks {
+            let records = sink.records();
+            assert_eq!(2, records.len());
+            assert!(records
+                .iter()
+                .all(|record| record.operation == AuditedOperation::Fetch));
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Implements a `DbInterface` adapter distributing lines among several
+//! databases.
+
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use futures_util::future::try_join_all;
+
+use super::{DbInterface, Token, TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens};
+
+/// Database distributing the lines among the given shards.
+///
+/// Each line is stored in the shard selected by its token. Since tokens are
+/// pseudo-random, lines are evenly distributed among shards. Operations
+/// involving several shards are sent to all of them concurrently.
+///
+/// Findex only requires upserts to be atomic per token: the conditional
+/// upsert of a given Entry Table line is performed by its shard only, and the
+/// rejected lines of each shard are returned together. However, a modification
+/// involving several shards is not atomic anymore: if one shard fails, the
+/// modifications performed by the other shards are not reverted.
+#[derive(Debug)]
+pub struct ShardedDb<Db> {
+    shards: Vec<Db>,
+}
+
+impl<Db> ShardedDb<Db> {
+    /// Distributes the lines among the given shards. The same shards must be
+    /// given in the same order each time, or lines will not be found.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no shard is given.
+    #[must_use]
+    pub fn new(shards: Vec<Db>) -> Self {
+        assert!(!shards.is_empty(), "at least one shard is needed");
+        Self { shards }
+    }
+
+    /// Returns the index of the shard storing the line of the given token.
+    fn shard_index(&self, token: &Token) -> usize {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&token[..8]);
+        (u64::from_be_bytes(bytes) % self.shards.len() as u64) as usize
+    }
+
+    /// Splits the given items among the shards.
+    fn split<T, C: Default + Extend<T>>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        token: impl Fn(&T) -> &Token,
+    ) -> Vec<C> {
+        let mut res = (0..self.shards.len())
+            .map(|_| C::default())
+            .collect::<Vec<_>>();
+        for item in items {
+            let i = self.shard_index(token(&item));
+            res[i].extend([item]);
+        }
+        res
+    }
+}
+
+#[async_trait(?Send)]
+impl<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>> DbInterface<VALUE_LENGTH>
+    for ShardedDb<Db>
+{
+    type Error = Db::Error;
+
+    async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+        let tokens = try_join_all(self.shards.iter().map(DbInterface::dump_tokens)).await?;
+        Ok(tokens.into_iter().flatten().collect())
+    }
+
+    async fn fetch(
+        &self,
+        tokens: Tokens,
+    ) -> Result<TokenWithEncryptedValueList<VALUE_LENGTH>, Self::Error> {
+        let tokens = self.split::<_, HashSet<_>>(tokens, |token| token);
+        let lines = try_join_all(
+            self.shards
+                .iter()
+                .zip(tokens)
+                .filter(|(_, tokens)| !tokens.is_empty())
+                .map(|(shard, tokens)| shard.fetch(tokens.into())),
+        )
+        .await?;
+        Ok(lines.into_iter().flatten().collect())
+    }
+
+    async fn upsert(
+        &self,
+        old_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+        new_values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<TokenToEncryptedValueMap<VALUE_LENGTH>, Self::Error> {
+        let old_values = self.split::<_, HashMap<_, _>>(old_values, |(token, _)| token);
+        let new_values = self.split::<_, HashMap<_, _>>(new_values, |(token, _)| token);
+        let rejected_values = try_join_all(
+            self.shards
+                .iter()
+                .zip(old_values.into_iter().zip(new_values))
+                .filter(|(_, (_, new_values))| !new_values.is_empty())
+                .map(|(shard, (old_values, new_values))| {
+                    shard.upsert(old_values.into(), new_values.into())
+                }),
+        )
+        .await?;
+        Ok(rejected_values.into_iter().flatten().collect())
+    }
+
+    async fn insert(
+        &self,
+        values: TokenToEncryptedValueMap<VALUE_LENGTH>,
+    ) -> Result<(), Self::Error> {
+        let values = self.split::<_, HashMap<_, _>>(values, |(token, _)| token);
+        try_join_all(
+            self.shards
+                .iter()
+                .zip(values)
+                .filter(|(_, values)| !values.is_empty())
+                .map(|(shard, values)| shard.insert(values.into())),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+        let tokens = self.split::<_, HashSet<_>>(tokens, |token| token);
+        try_join_all(
+            self.shards
+                .iter()
+                .zip(tokens)
+                .filter(|(_, tokens)| !tokens.is_empty())
+                .map(|(shard, tokens)| shard.delete(tokens.into())),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmian_crypto_core::{reexport::rand_core::SeedableRng, CsRng};
+
+    use super::*;
+    use crate::edx::in_memory::{
+        tests::{random_line, VALUE_LENGTH},
+        InMemoryDb,
+    };
+
+    crate::db_interface_conformance_tests!(
+        ShardedDb<InMemoryDb<VALUE_LENGTH>>,
+        ShardedDb::new(vec![InMemoryDb::default(), InMemoryDb::default()])
+    );
+
+    #[actix_rt::test]
+    async fn test_sharding() {
+        let mut rng = CsRng::from_entropy();
+        let n_shards = 4_usize;
+        let n_lines = 1_000;
+
+        let shards = (0..n_shards)
+            .map(|_| InMemoryDb::<VALUE_LENGTH>::default())
+            .collect::<Vec<_>>();
+        let db = ShardedDb::new(shards.clone());
+
+        let lines = (0..n_lines)
+            .map(|_| random_line(&mut rng))
+            .collect::<TokenToEncryptedValueMap<VALUE_LENGTH>>();
+        db.insert(lines.clone()).await.unwrap();
+
+        // Lines are evenly distributed.
+        for shard in &shards {
+            assert!((n_lines / n_shards).abs_diff(shard.len()) < n_lines / 10);
+        }
+        assert_eq!(n_lines, db.dump_tokens().await.unwrap().len());
+
+        let res = db
+            .fetch(lines.keys().copied().collect())
+            .await
+            .unwrap()
+            .into_iter()
+            .collect::<TokenToEncryptedValueMap<VALUE_LENGTH>>();
+        assert_eq!(lines, res);
+
+        // Rejected lines of all shards are returned.
+        let (new_tokens, new_values): (Vec<_>, Vec<_>) =
+            (0..n_shards).map(|_| random_line(&mut rng)).unzip();
+        let rejected = db
+            .upsert(
+                TokenToEncryptedValueMap::default(),
+                lines
+                    .keys()
+                    .copied()
+                    .zip(new_values.iter().cloned())
+                    .chain(new_tokens.iter().copied().zip(new_values.iter().cloned()))
+                    .collect(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(n_shards, rejected.len());
+        for (token, value) in rejected {
+            assert_eq!(Some(&value), lines.get(&token));
+        }
+
+        db.delete(lines.keys().copied().collect()).await.unwrap();
+        assert_eq!(n_shards, db.dump_tokens().await.unwrap().len());
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     collections::{HashMap, HashSet},
-    fmt::Display,
+    fmt::{Display, LowerHex},
     ops::{Deref, DerefMut},
     vec::IntoIter,
 };
@@ -23,6 +23,28 @@ pub struct Token([u8; TOKEN_LENGTH]);
 
 impl Token {
     pub const LENGTH: usize = TOKEN_LENGTH;
+
+    /// Returns the lowercase hexadecimal representation of this token.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        format!("{self:x}")
+    }
+
+    /// Parses a token from its hexadecimal representation.
+    pub fn from_hex(hex: &str) -> Result<Self, CoreError> {
+        if hex.len() != 2 * TOKEN_LENGTH || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(CoreError::Conversion(format!(
+                "cannot create token from '{hex}', {} hexadecimal characters expected",
+                2 * TOKEN_LENGTH
+            )));
+        }
+        let mut bytes = [0; TOKEN_LENGTH];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+                .map_err(|e| CoreError::Conversion(e.to_string()))?;
+        }
+        Ok(Self(bytes))
+    }
 }
 
 impl Deref for Token {
@@ -66,6 +88,15 @@ impl Display for Token {
     }
 }
 
+impl LowerHex for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Tokens(pub HashSet<Token>);
 
@@ -391,3 +422,34 @@ impl<const VALUE_LENGTH: usize> IntoIterator for TokenToEncryptedValueMap<VALUE_
         self.0.into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmian_crypto_core::{
+        reexport::rand_core::{RngCore, SeedableRng},
+        CsRng,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_token_hex() {
+        let mut rng = CsRng::from_entropy();
+        let mut bytes = [0; TOKEN_LENGTH];
+        rng.fill_bytes(&mut bytes);
+        let token = Token::from(bytes);
+
+        let hex = token.to_hex();
+        assert_eq!(hex.len(), 2 * TOKEN_LENGTH);
+        assert_eq!(hex, format!("{token:x}"));
+        assert_eq!(token, Token::from_hex(&hex).unwrap());
+        assert_eq!(token, Token::from_hex(&hex.to_uppercase()).unwrap());
+
+        // Wrong length.
+        assert!(Token::from_hex(&hex[2..]).is_err());
+        assert!(Token::from_hex(&format!("{hex}00")).is_err());
+        // Non-hexadecimal characters.
+        assert!(Token::from_hex(&format!("+f{}", &hex[2..])).is_err());
+        assert!(Token::from_hex(&format!("zz{}", &hex[2..])).is_err());
+    }
+}
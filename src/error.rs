@@ -5,7 +5,7 @@ use core::fmt::{Debug, Display};
 use cosmian_crypto_core::CryptoCoreError;
 use never::Never;
 
-pub trait DbInterfaceErrorTrait: std::error::Error {}
+pub trait DbInterfaceErrorTrait: std::error::Error + 'static {}
 
 #[derive(Debug)]
 pub enum Error<T: std::error::Error> {
@@ -15,6 +15,8 @@ pub enum Error<T: std::error::Error> {
     DbInterface(T),
     Interrupt(String),
     Filter(String),
+    EmptyKeyword,
+    MissingLinks(usize),
 }
 
 impl<T: std::error::Error> Display for Error<T> {
@@ -27,6 +29,11 @@ impl<T: std::error::Error> Display for Error<T> {
             Self::DbInterface(msg) => write!(f, "database interface error: {msg}"),
             Self::Interrupt(error) => write!(f, "user interrupt error: {error}"),
             Self::Filter(error) => write!(f, "user data filter error: {error}"),
+            Self::EmptyKeyword => write!(f, "keywords cannot be empty"),
+            Self::MissingLinks(n) => write!(
+                f,
+                "{n} links of the chain are missing, they may still be written by an addition"
+            ),
         }
     }
 }
@@ -49,7 +56,15 @@ impl<T: DbInterfaceErrorTrait> From<T> for Error<T> {
     }
 }
 
-impl<T: std::error::Error> std::error::Error for Error<T> {}
+impl<T: std::error::Error + 'static> std::error::Error for Error<T> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CryptoCore(err) => Some(err),
+            Self::DbInterface(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 /// Alias used to represent a Findex error that does not originate from a
 /// callback.
@@ -66,6 +81,8 @@ impl<T: DbInterfaceErrorTrait> From<CoreError> for Error<T> {
             }
             CoreError::Interrupt(err) => Self::Interrupt(err),
             CoreError::Filter(err) => Self::Filter(err),
+            CoreError::EmptyKeyword => Self::EmptyKeyword,
+            CoreError::MissingLinks(n) => Self::MissingLinks(n),
         }
     }
 }
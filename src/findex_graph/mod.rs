@@ -88,10 +88,7 @@ impl<
 {
     pub fn new(entry_table: EntryTable, chain_table: ChainTable) -> Self {
         Self {
-            findex_mm: FindexMultiMap {
-                entry_table,
-                chain_table,
-            },
+            findex_mm: FindexMultiMap::new(entry_table, chain_table),
         }
     }
 }
@@ -4,16 +4,21 @@ use std::{
 };
 
 use cosmian_crypto_core::reexport::rand_core::CryptoRngCore;
+use tiny_keccak::{Hasher, Sha3};
 use tracing::debug;
 
 use super::{structs::Entry, Operation};
 use crate::{
     edx::{Token, TokenDump},
     findex_mm::{structs::Link, CompactingData, FindexMultiMap, MmEnc},
-    parameters::{BLOCK_LENGTH, LINE_WIDTH, SEED_LENGTH},
+    parameters::{BLOCK_LENGTH, HASH_LENGTH, LINE_WIDTH, SEED_LENGTH},
     DbInterfaceErrorTrait, DxEnc, Error, Label, ENTRY_LENGTH, LINK_LENGTH,
 };
 
+/// Maximum number of times the chain of a tag is read by its compaction while
+/// some of its links are missing.
+const COMPACT_TAG_ATTEMPTS: usize = 3;
+
 impl<
         UserError: DbInterfaceErrorTrait,
         EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
@@ -131,6 +136,7 @@ impl<
         mut continuation: CompactingData<ChainTable>,
         new_label: &Label,
     ) -> Result<(), Error<UserError>> {
+        #[allow(clippy::unnecessary_map_or)]
         let remaining_entry_tokens = continuation
             .entries
             .keys()
@@ -195,7 +201,7 @@ impl<
             let rng = &mut *rng.lock().expect("could not lock mutex");
             for (token, entry) in continuation.entries {
                 old_entries.insert(token);
-                if remaining_entry_tokens.get(&token).is_some() {
+                if remaining_entry_tokens.contains(&token) {
                     new_entries.insert(
                         self.entry_table
                             .tokenize(new_key, &entry.tag_hash, Some(new_label)),
@@ -237,4 +243,134 @@ impl<
 
         res
     }
+
+    /// Compacts the chain associated to the given tag, keeping the same key
+    /// and label.
+    ///
+    /// The chain is re-written using a new seed, without the deleted values
+    /// and without internal padding. The new entry is conditionally upserted,
+    /// thus this operation can run concurrently with additions: if the entry
+    /// is modified in the meantime, the new chain is deleted and the
+    /// compaction is retried.
+    ///
+    /// Since additions write their links after committing their entry, a
+    /// missing link may still be written by an ongoing addition: the chain is
+    /// read again, and an [`Error::MissingLinks`] is returned if links are
+    /// still missing after [`COMPACT_TAG_ATTEMPTS`] reads. Only the links read
+    /// are deleted.
+    ///
+    /// Does nothing if the given tag is not indexed.
+    #[tracing::instrument(skip_all)]
+    pub async fn compact_tag<Tag: AsRef<[u8]>>(
+        &self,
+        rng: Arc<Mutex<impl CryptoRngCore>>,
+        key: &<Self as MmEnc<SEED_LENGTH, UserError>>::Key,
+        tag: &Tag,
+        label: &Label,
+    ) -> Result<(), Error<UserError>> {
+        let mut tag_hash = [0; HASH_LENGTH];
+        let mut hasher = Sha3::v256();
+        hasher.update(tag.as_ref());
+        hasher.finalize(&mut tag_hash);
+        let entry_token = self.entry_table.tokenize(key, &tag_hash, Some(label));
+
+        let mut n_reads = 0;
+        loop {
+            let Some((_, old_encrypted_entry)) = self
+                .entry_table
+                .get(HashSet::from_iter([entry_token]))
+                .await?
+                .pop()
+            else {
+                return Ok(());
+            };
+            let old_entry =
+                Entry::<ChainTable>::from(self.entry_table.resolve(key, &old_encrypted_entry)?);
+
+            let (old_chain_key, old_chain_tokens) = self.derive_metadata(&old_entry);
+            let old_encrypted_links = self
+                .chain_table
+                .get(old_chain_tokens.iter().copied().collect())
+                .await?
+                .into_iter()
+                .collect::<HashMap<_, _>>();
+            let n_missing_links = old_chain_tokens.len() - old_encrypted_links.len();
+            if 0 < n_missing_links {
+                n_reads += 1;
+                if n_reads == COMPACT_TAG_ATTEMPTS {
+                    return Err(Error::MissingLinks(n_missing_links));
+                }
+                debug!("compact_tag: {n_missing_links} links missing, reading the chain again");
+                continue;
+            }
+            let old_links = old_chain_tokens
+                .iter()
+                .filter_map(|token| old_encrypted_links.get(token))
+                .map(|ciphertext| {
+                    self.chain_table
+                        .resolve(&old_chain_key, ciphertext)
+                        .map(Link)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let values = self.recompose::<BLOCK_LENGTH, LINE_WIDTH>(&old_links)?;
+            let new_links = self.decompose::<BLOCK_LENGTH, LINE_WIDTH>(
+                &values
+                    .into_iter()
+                    .map(|v| (Operation::Addition, v))
+                    .collect::<Vec<_>>(),
+            )?;
+
+            let (new_encrypted_entry, new_encrypted_links) = {
+                let rng = &mut *rng.lock().expect("could not lock mutex");
+                let mut new_entry =
+                    Entry::<ChainTable>::new(self.chain_table.gen_seed(rng), tag_hash, None);
+                let new_chain_key = self.chain_table.derive_keys(&new_entry.seed);
+                let new_chain_tokens =
+                    self.derive_chain_tokens(&new_chain_key, tag_hash.into(), new_links.len());
+                new_entry.chain_token = new_chain_tokens.last().copied();
+                let new_encrypted_links = new_chain_tokens
+                    .into_iter()
+                    .zip(new_links)
+                    .map(|(token, link)| {
+                        self.chain_table
+                            .prepare(rng, &new_chain_key, link.0)
+                            .map(|ciphertext| (token, ciphertext))
+                    })
+                    .collect::<Result<HashMap<_, _>, _>>()?;
+                (
+                    self.entry_table.prepare(rng, key, new_entry.into())?,
+                    new_encrypted_links,
+                )
+            };
+            let new_link_tokens = new_encrypted_links.keys().copied().collect::<HashSet<_>>();
+
+            self.chain_table.insert(new_encrypted_links).await?;
+
+            let rejected_entries = self
+                .entry_table
+                .upsert(
+                    HashMap::from_iter([(entry_token, old_encrypted_entry)]),
+                    HashMap::from_iter([(entry_token, new_encrypted_entry)]),
+                )
+                .await;
+
+            match rejected_entries {
+                Ok(rejected_entries) if rejected_entries.is_empty() => {
+                    self.chain_table
+                        .delete(old_encrypted_links.into_keys().collect())
+                        .await?;
+                    return Ok(());
+                }
+                Ok(_) => {
+                    debug!("compact_tag: entry modified concurrently, retrying");
+                    self.chain_table.delete(new_link_tokens).await?;
+                }
+                Err(e) => {
+                    self.chain_table.delete(new_link_tokens).await?;
+                    return Err(e);
+                }
+            }
+        }
+    }
 }
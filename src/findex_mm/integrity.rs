@@ -0,0 +1,90 @@
+//! Implements the integrity check of the Findex multi-map.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    edx::{Token, TokenDump},
+    findex_mm::{
+        structs::{Entry, IntegrityIssue, Link},
+        FindexMultiMap, MmEnc,
+    },
+    parameters::{BLOCK_LENGTH, LINE_WIDTH, SEED_LENGTH},
+    DbInterfaceErrorTrait, DxEnc, Error, ENTRY_LENGTH, LINK_LENGTH,
+};
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+        ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+    > FindexMultiMap<UserError, EntryTable, ChainTable>
+{
+    /// Checks that all entries can be decrypted using the given key and that
+    /// their chains are complete and readable. Reports the links that are not
+    /// referenced by any entry.
+    ///
+    /// The entire index is read but nothing is modified. Since additions
+    /// write their links after committing their entries, concurrent additions
+    /// may be reported as missing links.
+    pub async fn verify_integrity(
+        &self,
+        key: &<Self as MmEnc<SEED_LENGTH, UserError>>::Key,
+    ) -> Result<Vec<IntegrityIssue>, Error<UserError>> {
+        let mut issues = Vec::new();
+
+        let entry_tokens = self.entry_table.dump_tokens().await?;
+        let mut chains = Vec::with_capacity(entry_tokens.len());
+        for (token, encrypted_entry) in self.entry_table.get(entry_tokens).await? {
+            match self.entry_table.resolve(key, &encrypted_entry) {
+                Ok(entry) => {
+                    chains.push((
+                        token,
+                        self.derive_metadata(&Entry::<ChainTable>::from(entry)),
+                    ));
+                }
+                Err(_) => issues.push(IntegrityIssue::UndecryptableEntry(token)),
+            }
+        }
+
+        let mut links = self
+            .chain_table
+            .get(
+                chains
+                    .iter()
+                    .flat_map(|(_, (_, chain_tokens))| chain_tokens)
+                    .copied()
+                    .collect(),
+            )
+            .await?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let mut referenced_links = HashSet::<Token>::new();
+        for (entry, (chain_key, chain_tokens)) in chains {
+            let mut chain_links = Vec::with_capacity(chain_tokens.len());
+            for link in chain_tokens {
+                referenced_links.insert(link);
+                match links.remove(&link) {
+                    None => issues.push(IntegrityIssue::MissingLink { entry, link }),
+                    Some(ciphertext) => match self.chain_table.resolve(&chain_key, &ciphertext) {
+                        Ok(value) => chain_links.push(Link(value)),
+                        Err(_) => issues.push(IntegrityIssue::UndecryptableLink { entry, link }),
+                    },
+                }
+            }
+            if self
+                .recompose::<BLOCK_LENGTH, LINE_WIDTH>(&chain_links)
+                .is_err()
+            {
+                issues.push(IntegrityIssue::MalformedChain(entry));
+            }
+        }
+
+        for link in self.chain_table.dump_tokens().await? {
+            if !referenced_links.contains(&link) {
+                issues.push(IntegrityIssue::OrphanLink(link));
+            }
+        }
+
+        Ok(issues)
+    }
+}
@@ -0,0 +1,122 @@
+//! Implements the merge of a Findex multi-map into another one.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use cosmian_crypto_core::reexport::rand_core::CryptoRngCore;
+
+use crate::{
+    edx::Token,
+    findex_mm::{structs::Link, FindexMultiMap, MmEnc, Operation},
+    parameters::{BLOCK_LENGTH, HASH_LENGTH, LINE_WIDTH, SEED_LENGTH},
+    DbInterfaceErrorTrait, DxEnc, Error, Label, ENTRY_LENGTH, LINK_LENGTH,
+};
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>>,
+        ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    > FindexMultiMap<UserError, EntryTable, ChainTable>
+{
+    /// Fetches the values indexed by the given entries, by tag hash.
+    ///
+    /// Deleted values and padding are not returned, and missing links are
+    /// skipped.
+    async fn fetch_values(
+        &self,
+        key: &<Self as MmEnc<SEED_LENGTH, UserError>>::Key,
+        tokens: HashSet<Token>,
+    ) -> Result<HashMap<[u8; HASH_LENGTH], HashSet<Vec<u8>>>, Error<UserError>> {
+        let chain_metadata = self
+            .fetch_entries(key, tokens)
+            .await?
+            .into_iter()
+            .map(|(_, entry)| (entry.tag_hash, self.derive_metadata(&entry)))
+            .collect::<HashMap<_, _>>();
+        let encrypted_links = self
+            .chain_table
+            .get(
+                chain_metadata
+                    .values()
+                    .flat_map(|(_, chain_tokens)| chain_tokens)
+                    .copied()
+                    .collect(),
+            )
+            .await?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        chain_metadata
+            .into_iter()
+            .map(|(tag_hash, (chain_key, chain_tokens))| {
+                let links = chain_tokens
+                    .iter()
+                    .filter_map(|token| encrypted_links.get(token))
+                    .map(|ciphertext| self.chain_table.resolve(&chain_key, ciphertext).map(Link))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let values = self.recompose::<BLOCK_LENGTH, LINE_WIDTH>(&links)?;
+                Ok((tag_hash, values))
+            })
+            .collect()
+    }
+
+    /// Adds the values indexed by the given entries of the `other` multi-map
+    /// to the chains of the same tags in this multi-map, using the given
+    /// label. The `other` multi-map is not modified.
+    ///
+    /// Deleted values, padding and missing links are not copied. The chains
+    /// of this multi-map are fetched first so that only the values they do
+    /// not already index are added.
+    #[tracing::instrument(skip_all)]
+    pub async fn merge_from<
+        OtherEntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>>,
+        OtherChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    >(
+        &self,
+        rng: Arc<Mutex<impl CryptoRngCore>>,
+        key: &<Self as MmEnc<SEED_LENGTH, UserError>>::Key,
+        label: &Label,
+        other: &FindexMultiMap<UserError, OtherEntryTable, OtherChainTable>,
+        other_key: &<FindexMultiMap<UserError, OtherEntryTable, OtherChainTable> as MmEnc<
+            SEED_LENGTH,
+            UserError,
+        >>::Key,
+        tokens: HashSet<Token>,
+    ) -> Result<(), Error<UserError>> {
+        let other_values = other.fetch_values(other_key, tokens).await?;
+        let indexed_values = self
+            .fetch_values(
+                key,
+                other_values
+                    .keys()
+                    .map(|tag_hash| self.entry_table.tokenize(key, tag_hash, Some(label)))
+                    .collect(),
+            )
+            .await?;
+
+        let mut modifications = HashMap::with_capacity(other_values.len());
+        for (tag_hash, values) in other_values {
+            let new_values = match indexed_values.get(&tag_hash) {
+                Some(indexed_values) => values.difference(indexed_values).cloned().collect(),
+                None => values.into_iter().collect::<Vec<_>>(),
+            };
+            if !new_values.is_empty() {
+                modifications.insert(
+                    tag_hash,
+                    (
+                        tag_hash,
+                        new_values
+                            .into_iter()
+                            .map(|value| (Operation::Addition, value))
+                            .collect(),
+                    ),
+                );
+            }
+        }
+
+        self.insert_hashed(rng, key, modifications, label).await?;
+        Ok(())
+    }
+}
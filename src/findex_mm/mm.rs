@@ -10,12 +10,16 @@ use std::{
 use async_trait::async_trait;
 use cosmian_crypto_core::reexport::rand_core::CryptoRngCore;
 use tiny_keccak::{Hasher, Sha3};
+use tracing::{trace, warn};
+use zeroize::Zeroize;
 
 use crate::{
-    edx::{DxEnc, Token},
+    edx::{line_length, DxEnc, Token},
     error::Error,
     findex_mm::{
-        structs::{Entry, Link, Operation},
+        structs::{
+            CommitCounters, CommitStats, CommittedEntry, Entry, Link, Operation, SearchPlan,
+        },
         FindexMultiMap, MmEnc, ENTRY_LENGTH, LINK_LENGTH,
     },
     parameters::{BLOCK_LENGTH, HASH_LENGTH, LINE_WIDTH, SEED_LENGTH},
@@ -33,9 +37,16 @@ impl<
         Self {
             entry_table,
             chain_table,
+            counters: CommitCounters::default(),
         }
     }
 
+    /// Returns the statistics about the Entry Table modifications performed
+    /// by this multi-map since its instantiation.
+    pub fn commit_stats(&self) -> CommitStats {
+        self.counters.stats()
+    }
+
     /// Derives all chain tokens from the given `seed` and `key`.
     pub(crate) fn unroll(
         &self,
@@ -98,6 +109,59 @@ impl<
             .collect())
     }
 
+    /// Fetches the values associated to the given tags, and accounts the
+    /// database accesses performed in the given plan.
+    pub(crate) async fn get_with_plan<Tag: Debug + Clone + Hash + Eq + AsRef<[u8]>>(
+        &self,
+        key: &EntryTable::Key,
+        tags: HashSet<Tag>,
+        label: &Label,
+        plan: &mut SearchPlan,
+    ) -> Result<HashMap<Tag, HashSet<Vec<u8>>>, Error<UserError>> {
+        plan.round_trips += 1;
+        plan.addresses_read += tags.len();
+        let entries = self.fetch_entries_by_tag(key, tags, label).await?;
+        plan.bytes += entries.len() * line_length::<ENTRY_LENGTH>();
+
+        let chain_metadata = entries
+            .into_iter()
+            .map(|(tag, entry)| (tag, self.derive_metadata(&entry)))
+            .collect::<Vec<_>>();
+
+        let chain_tokens = chain_metadata
+            .iter()
+            .flat_map(|(_, (_, tokens))| tokens)
+            .copied()
+            .collect::<HashSet<_>>();
+        plan.round_trips += 1;
+        plan.addresses_read += chain_tokens.len();
+        plan.chain_length += chain_tokens.len();
+        let links = self
+            .chain_table
+            .get(chain_tokens)
+            .await?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        plan.bytes += links.len() * line_length::<LINK_LENGTH>();
+
+        let mut indexed_values =
+            HashMap::<Tag, HashSet<Vec<u8>>>::with_capacity(chain_metadata.len());
+
+        for (tag, (chain_key, chain_tokens)) in chain_metadata {
+            let chain_links = chain_tokens
+                .iter()
+                .filter_map(|token| links.get(token))
+                .map(|ciphertext| self.chain_table.resolve(&chain_key, ciphertext).map(Link))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            indexed_values
+                .entry(tag)
+                .or_default()
+                .extend(self.recompose::<BLOCK_LENGTH, LINE_WIDTH>(&chain_links)?);
+        }
+        Ok(indexed_values)
+    }
+
     /// Fetches the Entry Table for the given tokens and decrypts the entries
     /// using the given key.
     pub(crate) async fn fetch_entries(
@@ -125,6 +189,10 @@ impl<
     /// Pads each value into blocks and push these blocks into a chain link,
     /// setting the flag bytes of each block according to the associated
     /// operation.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if a value is empty.
     pub(crate) fn decompose<const BLOCK_LENGTH: usize, const LINE_LENGTH: usize>(
         &self,
         modifications: &[(Operation, <Self as MmEnc<SEED_LENGTH, UserError>>::Item)],
@@ -135,6 +203,13 @@ impl<
         let mut pos = 0;
 
         for (operation, value) in modifications {
+            // Padding blocks are empty deletions: an empty value would be
+            // removed by the padding of its own link.
+            if value.is_empty() {
+                return Err(CoreError::Conversion(
+                    "empty values cannot be stored in the Chain Table".to_string(),
+                ));
+            }
             let full_block_number = value.len() / BLOCK_LENGTH;
 
             for i in 0..full_block_number {
@@ -209,7 +284,11 @@ impl<
                     if Operation::Addition == operation {
                         indexed_values.insert(findex_value);
                     } else {
-                        indexed_values.remove(&findex_value);
+                        // Deleted values are not returned: clear them.
+                        if let Some(mut deleted_value) = indexed_values.take(&findex_value) {
+                            deleted_value.zeroize();
+                        }
+                        findex_value.zeroize();
                     }
 
                     current_operation = None;
@@ -225,6 +304,116 @@ impl<
         Ok(indexed_values)
     }
 
+    /// Inserts the given modifications into the chains of the given tag
+    /// hashes. Tags are only used to identify the modifications.
+    ///
+    /// Returns the tags added as new entries to the Entry Table.
+    #[allow(clippy::type_complexity)]
+    pub(crate) async fn insert_hashed<Tag: Clone + Hash + Eq>(
+        &self,
+        rng: Arc<Mutex<impl CryptoRngCore>>,
+        key: &EntryTable::Key,
+        modifications: HashMap<Tag, ([u8; HASH_LENGTH], Vec<(Operation, Vec<u8>)>)>,
+        label: &Label,
+    ) -> Result<HashSet<Tag>, Error<UserError>> {
+        let chain_additions = modifications
+            .into_iter()
+            .map(|(tag, (tag_hash, new_values))| {
+                self.decompose::<BLOCK_LENGTH, LINE_WIDTH>(&new_values)
+                    .map(|links| (tag, (tag_hash, links)))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        let (new_tags, mut chain_tokens, committed_entries) = self
+            .commit(rng.clone(), key, label, &chain_additions)
+            .await?;
+
+        let mut encrypted_links = HashMap::with_capacity(
+            chain_tokens
+                .values()
+                .map(|(_, chain_tokens)| chain_tokens.len())
+                .sum(),
+        );
+
+        let mut link_tokens = HashMap::with_capacity(chain_additions.len());
+
+        for (tag, (_, links)) in chain_additions {
+            let (chain_key, tokens) = chain_tokens.remove(&tag).ok_or_else(|| {
+                CoreError::Crypto("no token not found for tag {tag:?}".to_string())
+            })?;
+            for (token, link) in tokens.iter().zip(links) {
+                encrypted_links.insert(
+                    *token,
+                    self.chain_table.prepare(
+                        &mut *rng.lock().expect("could not lock mutex"),
+                        &chain_key,
+                        link.0,
+                    )?,
+                );
+            }
+            link_tokens.insert(tag, tokens);
+        }
+
+        if let Err(err) = self.chain_table.insert(encrypted_links).await {
+            // Some links may have been written before the failure.
+            if let Err(rollback_err) = self
+                .rollback(rng, key, committed_entries, link_tokens)
+                .await
+            {
+                warn!("could not roll back the failed insertion: {rollback_err}");
+            }
+            return Err(err);
+        }
+
+        Ok(new_tags)
+    }
+
+    /// Rolls back the given committed entries after the failed insertion of
+    /// the given chain links.
+    ///
+    /// Each entry is conditionally restored to its previous value, or to an
+    /// empty chain if it was created by the insertion, then the links written
+    /// for it are deleted. Entries modified concurrently in the meantime are
+    /// kept along with their links since later additions extend their chain:
+    /// the missing links are skipped upon reading.
+    async fn rollback<Tag: Hash + Eq>(
+        &self,
+        rng: Arc<Mutex<impl CryptoRngCore>>,
+        key: &EntryTable::Key,
+        committed_entries: HashMap<Tag, CommittedEntry<EntryTable::EncryptedValue>>,
+        mut link_tokens: HashMap<Tag, Vec<Token>>,
+    ) -> Result<(), Error<UserError>> {
+        let mut tags = HashMap::with_capacity(committed_entries.len());
+        let mut old_values = HashMap::with_capacity(committed_entries.len());
+        let mut new_values = HashMap::with_capacity(committed_entries.len());
+        for (tag, entry) in committed_entries {
+            let restored_value = if let Some(old_value) = entry.old_value {
+                old_value
+            } else {
+                let mut empty_entry =
+                    Entry::<ChainTable>::from(self.entry_table.resolve(key, &entry.new_value)?);
+                empty_entry.chain_token = None;
+                self.entry_table.prepare(
+                    &mut *rng.lock().expect("could not lock mutex"),
+                    key,
+                    empty_entry.into(),
+                )?
+            };
+            tags.insert(entry.token, tag);
+            old_values.insert(entry.token, entry.new_value);
+            new_values.insert(entry.token, restored_value);
+        }
+
+        let rejected_entries = self.entry_table.upsert(old_values, new_values).await?;
+        let orphan_links = tags
+            .into_iter()
+            .filter(|(token, _)| !rejected_entries.contains_key(token))
+            .filter_map(|(_, tag)| link_tokens.remove(&tag))
+            .flatten()
+            .collect();
+        self.chain_table.delete(orphan_links).await
+    }
+
     /// Derives the chain metadata from the given entry:
     /// - the chain key
     /// - the chain tokens
@@ -243,27 +432,32 @@ impl<
 
     /// Commits the given chain modifications into the Entry Table.
     ///
-    /// Returns the chains to insert in the Chain Table.
-    async fn commit<Tag: Clone + Hash + Eq + AsRef<[u8]>>(
+    /// Returns the chains to insert in the Chain Table, and the entries
+    /// written.
+    #[allow(clippy::type_complexity)]
+    async fn commit<Tag: Clone + Hash + Eq>(
         &self,
         rng: Arc<Mutex<impl CryptoRngCore>>,
         key: &EntryTable::Key,
         label: &Label,
-        chain_additions: &HashMap<Tag, Vec<Link>>,
-    ) -> Result<(HashSet<Tag>, HashMap<Tag, (ChainTable::Key, Vec<Token>)>), Error<UserError>> {
+        chain_additions: &HashMap<Tag, ([u8; HASH_LENGTH], Vec<Link>)>,
+    ) -> Result<
+        (
+            HashSet<Tag>,
+            HashMap<Tag, (ChainTable::Key, Vec<Token>)>,
+            HashMap<Tag, CommittedEntry<EntryTable::EncryptedValue>>,
+        ),
+        Error<UserError>,
+    > {
         // Compute the token associated to the modifications.
         let mut chain_additions = chain_additions
             .iter()
-            .map(|(tag, links)| {
-                let mut tag_hash = [0; HASH_LENGTH];
-                let mut hasher = Sha3::v256();
-                hasher.update(tag.as_ref());
-                hasher.finalize(&mut tag_hash);
+            .map(|(tag, (tag_hash, links))| {
                 (
                     tag,
                     (
-                        self.entry_table.tokenize(key, &tag_hash, Some(label)),
-                        tag_hash,
+                        self.entry_table.tokenize(key, tag_hash, Some(label)),
+                        *tag_hash,
                         links.len(),
                     ),
                 )
@@ -298,9 +492,13 @@ impl<
 
         let mut new_tags = HashSet::with_capacity(chain_additions.len());
         let mut chain = HashMap::with_capacity(chain_additions.len());
+        let mut committed_entries = HashMap::with_capacity(chain_additions.len());
 
+        let mut attempt = 0;
         while !chain_additions.is_empty() {
+            attempt += 1;
             let mut new_entries = HashMap::with_capacity(chain_additions.len());
+            let mut attempted_entries = HashMap::with_capacity(chain_additions.len());
             // Compute new chain tokens to insert modifications and update the associated
             // entry. Create one if the associated tag was not indexed yet.
             for (tag, (token, tag_hash, n_additions)) in &chain_additions {
@@ -330,26 +528,43 @@ impl<
                 entry.chain_token = chain_tokens.last().copied();
 
                 chain.insert((*tag).clone(), (chain_key, chain_tokens));
-                new_entries.insert(
-                    *token,
-                    self.entry_table.prepare(
-                        &mut *rng.lock().expect("could not lock mutex"),
-                        key,
-                        entry.into(),
-                    )?,
+                let new_entry = self.entry_table.prepare(
+                    &mut *rng.lock().expect("could not lock mutex"),
+                    key,
+                    entry.into(),
+                )?;
+                attempted_entries.insert(
+                    (*tag).clone(),
+                    CommittedEntry {
+                        token: *token,
+                        old_value: encrypted_entries.get(token).cloned(),
+                        new_value: new_entry.clone(),
+                    },
                 );
+                new_entries.insert(*token, new_entry);
             }
 
             // 2 - Upsert new entries to the Entry Table.
+            let n_entries = new_entries.len();
             encrypted_entries = self
                 .entry_table
                 .upsert(encrypted_entries, new_entries)
                 .await?;
+            self.counters
+                .record(n_entries - encrypted_entries.len(), encrypted_entries.len());
+            for token in encrypted_entries.keys() {
+                trace!(%token, attempt, "entry upsert rejected by a concurrent modification");
+            }
+            committed_entries.extend(
+                attempted_entries
+                    .into_iter()
+                    .filter(|(_, entry)| !encrypted_entries.contains_key(&entry.token)),
+            );
             chain_additions.retain(|_, (k, _, _)| encrypted_entries.contains_key(k));
             new_tags.retain(|tag| !chain_additions.contains_key(tag));
         }
 
-        Ok((new_tags, chain))
+        Ok((new_tags, chain, committed_entries))
     }
 }
 
@@ -379,42 +594,8 @@ impl<
         tags: HashSet<Tag>,
         label: &Label,
     ) -> Result<HashMap<Tag, HashSet<Self::Item>>, Self::Error> {
-        let entries = self.fetch_entries_by_tag(key, tags, label).await?;
-
-        let chain_metadata = entries
-            .into_iter()
-            .map(|(tag, entry)| (tag, self.derive_metadata(&entry)))
-            .collect::<Vec<_>>();
-
-        let links = self
-            .chain_table
-            .get(
-                chain_metadata
-                    .iter()
-                    .flat_map(|(_, (_, tokens))| tokens)
-                    .copied()
-                    .collect(),
-            )
-            .await?
-            .into_iter()
-            .collect::<HashMap<_, _>>();
-
-        let mut indexed_values =
-            HashMap::<Tag, HashSet<Self::Item>>::with_capacity(chain_metadata.len());
-
-        for (tag, (chain_key, chain_tokens)) in chain_metadata {
-            let chain_links = chain_tokens
-                .iter()
-                .filter_map(|token| links.get(token))
-                .map(|ciphertext| self.chain_table.resolve(&chain_key, ciphertext).map(Link))
-                .collect::<Result<Vec<_>, _>>()?;
-
-            indexed_values
-                .entry(tag)
-                .or_default()
-                .extend(self.recompose::<BLOCK_LENGTH, LINE_WIDTH>(&chain_links)?);
-        }
-        Ok(indexed_values)
+        self.get_with_plan(key, tags, label, &mut SearchPlan::default())
+            .await
     }
 
     async fn insert<Tag: Clone + Hash + Eq + AsRef<[u8]>>(
@@ -424,44 +605,17 @@ impl<
         modifications: HashMap<Tag, Vec<(Operation, Self::Item)>>,
         label: &Label,
     ) -> Result<HashSet<Tag>, Self::Error> {
-        let chain_additions = modifications
+        let modifications = modifications
             .into_iter()
             .map(|(tag, new_values)| {
-                self.decompose::<BLOCK_LENGTH, LINE_WIDTH>(&new_values)
-                    .map(|links| (tag, links))
+                let mut tag_hash = [0; HASH_LENGTH];
+                let mut hasher = Sha3::v256();
+                hasher.update(tag.as_ref());
+                hasher.finalize(&mut tag_hash);
+                (tag, (tag_hash, new_values))
             })
-            .collect::<Result<HashMap<Tag, Vec<Link>>, _>>()?;
-
-        let (new_tags, mut chain_tokens) = self
-            .commit(rng.clone(), key, label, &chain_additions)
-            .await?;
-
-        let mut encrypted_links = HashMap::with_capacity(
-            chain_tokens
-                .values()
-                .map(|(_, chain_tokens)| chain_tokens.len())
-                .sum(),
-        );
-
-        for (tag, links) in chain_additions {
-            let (chain_key, tokens) = chain_tokens.remove(&tag).ok_or_else(|| {
-                CoreError::Crypto("no token not found for tag {tag:?}".to_string())
-            })?;
-            for (token, link) in tokens.into_iter().zip(links.into_iter()) {
-                encrypted_links.insert(
-                    token,
-                    self.chain_table.prepare(
-                        &mut *rng.lock().expect("could not lock mutex"),
-                        &chain_key,
-                        link.0,
-                    )?,
-                );
-            }
-        }
-
-        self.chain_table.insert(encrypted_links).await?;
-
-        Ok(new_tags)
+            .collect();
+        self.insert_hashed(rng, key, modifications, label).await
     }
 }
 
@@ -471,9 +625,374 @@ mod tests {
         reexport::rand_core::{RngCore, SeedableRng},
         CsRng,
     };
+    use futures::channel::oneshot;
 
     use super::*;
-    use crate::edx::{chain_table::ChainTable, entry_table::EntryTable, in_memory::InMemoryDb};
+    use crate::edx::{
+        audit::AuditedOperation,
+        chain_table::ChainTable,
+        entry_table::EntryTable,
+        faulty::{FaultyDb, FaultyDbError},
+        in_memory::{InMemoryDb, InMemoryDbError},
+        shard::ShardedDb,
+        DbInterface, TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens,
+    };
+
+    /// Database rejecting the next upsert as if a concurrent writer had
+    /// written the same values in the meantime.
+    #[derive(Debug, Default)]
+    struct ConcurrentDb {
+        db: InMemoryDb<ENTRY_LENGTH>,
+        reject_next_upsert: Mutex<bool>,
+    }
+
+    #[async_trait(?Send)]
+    impl DbInterface<ENTRY_LENGTH> for ConcurrentDb {
+        type Error = InMemoryDbError;
+
+        async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+            self.db.dump_tokens().await
+        }
+
+        async fn fetch(
+            &self,
+            tokens: Tokens,
+        ) -> Result<TokenWithEncryptedValueList<ENTRY_LENGTH>, Self::Error> {
+            self.db.fetch(tokens).await
+        }
+
+        async fn upsert(
+            &self,
+            old_values: TokenToEncryptedValueMap<ENTRY_LENGTH>,
+            new_values: TokenToEncryptedValueMap<ENTRY_LENGTH>,
+        ) -> Result<TokenToEncryptedValueMap<ENTRY_LENGTH>, Self::Error> {
+            let reject = std::mem::take(&mut *self.reject_next_upsert.lock().unwrap());
+            if reject {
+                let stored_values = self.db.fetch(new_values.keys().copied().collect()).await?;
+                Ok(stored_values.into_iter().collect())
+            } else {
+                self.db.upsert(old_values, new_values).await
+            }
+        }
+
+        async fn insert(
+            &self,
+            values: TokenToEncryptedValueMap<ENTRY_LENGTH>,
+        ) -> Result<(), Self::Error> {
+            self.db.insert(values).await
+        }
+
+        async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+            self.db.delete(tokens).await
+        }
+    }
+
+    /// Database whose next insertion waits for a signal before being applied.
+    struct GatedDb {
+        db: InMemoryDb<LINK_LENGTH>,
+        gate: Mutex<Option<oneshot::Receiver<()>>>,
+    }
+
+    #[async_trait(?Send)]
+    impl DbInterface<LINK_LENGTH> for GatedDb {
+        type Error = InMemoryDbError;
+
+        async fn dump_tokens(&self) -> Result<Tokens, Self::Error> {
+            self.db.dump_tokens().await
+        }
+
+        async fn fetch(
+            &self,
+            tokens: Tokens,
+        ) -> Result<TokenWithEncryptedValueList<LINK_LENGTH>, Self::Error> {
+            self.db.fetch(tokens).await
+        }
+
+        async fn upsert(
+            &self,
+            old_values: TokenToEncryptedValueMap<LINK_LENGTH>,
+            new_values: TokenToEncryptedValueMap<LINK_LENGTH>,
+        ) -> Result<TokenToEncryptedValueMap<LINK_LENGTH>, Self::Error> {
+            self.db.upsert(old_values, new_values).await
+        }
+
+        async fn insert(
+            &self,
+            values: TokenToEncryptedValueMap<LINK_LENGTH>,
+        ) -> Result<(), Self::Error> {
+            let gate = self.gate.lock().expect("could not lock mutex").take();
+            if let Some(gate) = gate {
+                gate.await.expect("gate sender dropped");
+            }
+            self.db.insert(values).await
+        }
+
+        async fn delete(&self, tokens: Tokens) -> Result<(), Self::Error> {
+            self.db.delete(tokens).await
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_commit_stats() {
+        let rng = Arc::new(Mutex::new(CsRng::from_entropy()));
+        let label = Label::random(&mut *rng.lock().unwrap());
+
+        let entry_table = EntryTable::setup(ConcurrentDb::default());
+        let chain_table = ChainTable::setup(InMemoryDb::default());
+        let findex = FindexMultiMap::new(entry_table, chain_table);
+        let seed = findex.gen_seed(&mut *rng.lock().unwrap());
+        let key = findex.derive_keys(&seed);
+
+        let tag = b"tag".to_vec();
+        for i in 0..2 {
+            let modifications = HashMap::from_iter([(
+                tag.clone(),
+                vec![(Operation::Addition, format!("value {i}").into_bytes())],
+            )]);
+            findex
+                .insert(rng.clone(), &key, modifications, &label)
+                .await
+                .unwrap();
+            assert_eq!(i, findex.commit_stats().n_retries);
+            assert_eq!(i + 1, findex.commit_stats().n_commits);
+
+            // Simulate a concurrent addition during the next insertion.
+            *findex.entry_table.0.reject_next_upsert.lock().unwrap() = true;
+        }
+
+        let res = findex
+            .get(&key, HashSet::from_iter([tag.clone()]), &label)
+            .await
+            .unwrap();
+        assert_eq!(2, res[&tag].len());
+    }
+
+    #[test]
+    fn test_link_zeroization() {
+        fn assert_zeroize_on_drop<T: zeroize::ZeroizeOnDrop>() {}
+        assert_zeroize_on_drop::<Link>();
+
+        let mut link = Link([1; LINK_LENGTH]);
+        link.zeroize();
+        assert_eq!(link.0, [0; LINK_LENGTH]);
+    }
+
+    /// Subscriber counting the events having an `attempt` field.
+    #[derive(Debug, Default)]
+    struct RejectionCounter(Arc<Mutex<usize>>);
+
+    impl tracing::Subscriber for RejectionCounter {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            if event.metadata().fields().field("attempt").is_some() {
+                *self.0.lock().unwrap() += 1;
+            }
+        }
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[test]
+    fn test_upsert_rejection_trace() {
+        let rng = Arc::new(Mutex::new(CsRng::from_entropy()));
+        let label = Label::random(&mut *rng.lock().unwrap());
+
+        let entry_table = EntryTable::setup(ConcurrentDb::default());
+        let chain_table = ChainTable::setup(InMemoryDb::default());
+        let findex = FindexMultiMap::new(entry_table, chain_table);
+        let seed = findex.gen_seed(&mut *rng.lock().unwrap());
+        let key = findex.derive_keys(&seed);
+
+        let tag = b"tag".to_vec();
+        let n_rejections = Arc::new(Mutex::new(0));
+        let subscriber = RejectionCounter(n_rejections.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..2 {
+                let modifications = HashMap::from_iter([(
+                    tag.clone(),
+                    vec![(Operation::Addition, format!("value {i}").into_bytes())],
+                )]);
+                futures::executor::block_on(findex.insert(
+                    rng.clone(),
+                    &key,
+                    modifications,
+                    &label,
+                ))
+                .unwrap();
+                assert_eq!(i, *n_rejections.lock().unwrap());
+
+                // Simulate a concurrent addition during the next insertion.
+                *findex.entry_table.0.reject_next_upsert.lock().unwrap() = true;
+            }
+        });
+    }
+
+    #[actix_rt::test]
+    async fn test_chain_insert_rollback() {
+        let rng = Arc::new(Mutex::new(CsRng::from_entropy()));
+        let label = Label::random(&mut *rng.lock().unwrap());
+
+        // The second shard fails the first insert while the first one
+        // succeeds: the chain is partially written.
+        let shards = [InMemoryDb::default(), InMemoryDb::default()];
+        let entry_table = EntryTable::setup(FaultyDb::scripted(InMemoryDb::default(), []));
+        let chain_table = ChainTable::setup(ShardedDb::new(vec![
+            FaultyDb::scripted(shards[0].clone(), []),
+            FaultyDb::scripted(shards[1].clone(), [(AuditedOperation::Insert, 1)]),
+        ]));
+        let findex = FindexMultiMap::new(entry_table, chain_table);
+        let seed = findex.gen_seed(&mut *rng.lock().unwrap());
+        let key = findex.derive_keys(&seed);
+
+        let modifications = HashMap::from_iter([(
+            b"tag".to_vec(),
+            (0..50)
+                .map(|i| (Operation::Addition, format!("value {i}").into_bytes()))
+                .collect(),
+        )]);
+        let res = findex
+            .insert(rng.clone(), &key, modifications.clone(), &label)
+            .await;
+        assert!(
+            matches!(
+                res,
+                Err(Error::DbInterface(FaultyDbError::Injected(
+                    AuditedOperation::Insert
+                )))
+            ),
+            "{res:?}"
+        );
+
+        // No link is left in the Chain Table, and the new entry points to an
+        // empty chain.
+        assert_eq!(0, shards[0].len());
+        assert_eq!(0, shards[1].len());
+        let res = findex
+            .get(&key, HashSet::from_iter([b"tag".to_vec()]), &label)
+            .await
+            .unwrap();
+        assert!(res.values().all(HashSet::is_empty), "{res:?}");
+        assert_eq!(findex.verify_integrity(&key).await.unwrap(), vec![]);
+
+        // The same chain can be written again.
+        findex
+            .insert(rng, &key, modifications, &label)
+            .await
+            .unwrap();
+        let res = findex
+            .get(&key, HashSet::from_iter([b"tag".to_vec()]), &label)
+            .await
+            .unwrap();
+        assert_eq!(res[&b"tag".to_vec()].len(), 50);
+    }
+
+    #[actix_rt::test]
+    async fn test_compact_tag_concurrent_addition() {
+        let rng = Arc::new(Mutex::new(CsRng::from_entropy()));
+        let label = Label::random(&mut *rng.lock().unwrap());
+
+        let entry_table = EntryTable::setup(InMemoryDb::default());
+        let chain_table = ChainTable::setup(GatedDb {
+            db: InMemoryDb::default(),
+            gate: Mutex::new(None),
+        });
+        let findex = FindexMultiMap::new(entry_table, chain_table);
+        let seed = findex.gen_seed(&mut *rng.lock().unwrap());
+        let key = findex.derive_keys(&seed);
+
+        let tag = b"tag".to_vec();
+        let addition = |value: &str| {
+            HashMap::from_iter([(
+                tag.clone(),
+                vec![(Operation::Addition, value.as_bytes().to_vec())],
+            )])
+        };
+        findex
+            .insert(rng.clone(), &key, addition("value 1"), &label)
+            .await
+            .unwrap();
+
+        // The next addition commits its entry, then waits for the compaction
+        // to return before writing its link.
+        let (sender, receiver) = oneshot::channel();
+        *findex.chain_table.gate.lock().unwrap() = Some(receiver);
+        let (insert_res, compact_res) = futures::join!(
+            findex.insert(rng.clone(), &key, addition("value 2"), &label),
+            async {
+                let res = findex.compact_tag(rng.clone(), &key, &tag, &label).await;
+                sender.send(()).unwrap();
+                res
+            }
+        );
+        insert_res.unwrap();
+        assert!(
+            matches!(compact_res, Err(Error::MissingLinks(1))),
+            "{compact_res:?}"
+        );
+
+        // The value of the concurrent addition is kept, and the chain can be
+        // compacted once written.
+        let expected_values = HashSet::from_iter([b"value 1".to_vec(), b"value 2".to_vec()]);
+        let res = findex
+            .get(&key, HashSet::from_iter([tag.clone()]), &label)
+            .await
+            .unwrap();
+        assert_eq!(res[&tag], expected_values);
+        findex.compact_tag(rng, &key, &tag, &label).await.unwrap();
+        let res = findex
+            .get(&key, HashSet::from_iter([tag.clone()]), &label)
+            .await
+            .unwrap();
+        assert_eq!(res[&tag], expected_values);
+        assert_eq!(1, findex.chain_table.db.len());
+        assert_eq!(findex.verify_integrity(&key).await.unwrap(), vec![]);
+    }
+
+    #[actix_rt::test]
+    async fn test_failed_rollback() {
+        let rng = Arc::new(Mutex::new(CsRng::from_entropy()));
+        let label = Label::random(&mut *rng.lock().unwrap());
+
+        // The deletion of the links written fails as well.
+        let entry_table = EntryTable::setup(FaultyDb::scripted(InMemoryDb::default(), []));
+        let chain_table = ChainTable::setup(FaultyDb::scripted(
+            InMemoryDb::default(),
+            [(AuditedOperation::Insert, 1), (AuditedOperation::Delete, 1)],
+        ));
+        let findex = FindexMultiMap::new(entry_table, chain_table);
+        let seed = findex.gen_seed(&mut *rng.lock().unwrap());
+        let key = findex.derive_keys(&seed);
+
+        let modifications = HashMap::from_iter([(
+            b"tag".to_vec(),
+            vec![(Operation::Addition, b"value".to_vec())],
+        )]);
+        let res = findex.insert(rng, &key, modifications, &label).await;
+
+        // The error of the insertion is returned.
+        assert!(
+            matches!(
+                res,
+                Err(Error::DbInterface(FaultyDbError::Injected(
+                    AuditedOperation::Insert
+                )))
+            ),
+            "{res:?}"
+        );
+    }
 
     #[actix_rt::test]
     async fn test_decompose_recompose() {
@@ -504,4 +1023,55 @@ mod tests {
             .unwrap();
         assert_eq!(values, res);
     }
+
+    #[actix_rt::test]
+    async fn test_decompose_recompose_random_sets() {
+        let mut seed = [0; 32];
+        CsRng::from_entropy().fill_bytes(&mut seed);
+        let mut rng = CsRng::from_seed(seed);
+
+        let entry_table = EntryTable::setup(InMemoryDb::default());
+        let chain_table = ChainTable::setup(InMemoryDb::default());
+        let findex = FindexMultiMap::new(entry_table, chain_table);
+
+        // Empty values cannot be told apart from padding.
+        assert!(findex
+            .decompose::<BLOCK_LENGTH, LINE_WIDTH>(&[(Operation::Addition, vec![])])
+            .is_err());
+
+        let mut value_sets = vec![
+            HashSet::new(),
+            HashSet::from_iter([vec![1]]),
+            HashSet::from_iter([vec![1; BLOCK_LENGTH], vec![2; 2 * BLOCK_LENGTH]]),
+            HashSet::from_iter([vec![3; LINE_WIDTH * BLOCK_LENGTH]]),
+            HashSet::from_iter([vec![4; 100 * BLOCK_LENGTH + 1]]),
+        ];
+        for _ in 0..100 {
+            let n_values = rng.next_u32() % 20;
+            value_sets.push(
+                (0..n_values)
+                    .map(|_| {
+                        let mut value = vec![0; 1 + (rng.next_u32() % 200) as usize];
+                        rng.fill_bytes(&mut value);
+                        value
+                    })
+                    .collect(),
+            );
+        }
+
+        for values in value_sets {
+            let lines = findex
+                .decompose::<BLOCK_LENGTH, LINE_WIDTH>(
+                    &values
+                        .iter()
+                        .map(|v| (Operation::Addition, v.clone()))
+                        .collect::<Vec<_>>(),
+                )
+                .unwrap();
+            let res = findex
+                .recompose::<BLOCK_LENGTH, LINE_WIDTH>(&lines)
+                .unwrap();
+            assert_eq!(values, res, "seed: {seed:?}");
+        }
+    }
 }
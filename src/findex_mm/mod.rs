@@ -35,10 +35,16 @@ use zeroize::ZeroizeOnDrop;
 use crate::{edx::DxEnc, DbInterfaceErrorTrait, Error, Label};
 
 mod compact;
+mod integrity;
+mod merge;
 mod mm;
+mod rebuild;
 mod structs;
 
-pub use structs::{CompactingData, Operation, ENTRY_LENGTH, LINK_LENGTH};
+use structs::CommitCounters;
+pub use structs::{
+    CommitStats, CompactingData, IntegrityIssue, Operation, SearchPlan, ENTRY_LENGTH, LINK_LENGTH,
+};
 
 #[async_trait(?Send)]
 pub trait MmEnc<const SEED_LENGTH: usize, EdxError: DbInterfaceErrorTrait> {
@@ -88,6 +94,7 @@ pub struct FindexMultiMap<
 > {
     pub entry_table: EntryTable,
     pub chain_table: ChainTable,
+    counters: CommitCounters,
 }
 
 #[cfg(test)]
@@ -0,0 +1,137 @@
+//! Implements the migration of the Findex multi-map to new tables.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use cosmian_crypto_core::reexport::rand_core::CryptoRngCore;
+use tracing::debug;
+
+use crate::{
+    edx::Token,
+    findex_mm::{
+        structs::{Entry, Link},
+        FindexMultiMap, MmEnc, Operation,
+    },
+    parameters::{BLOCK_LENGTH, LINE_WIDTH, SEED_LENGTH},
+    DbInterfaceErrorTrait, DxEnc, Error, Label, ENTRY_LENGTH, LINK_LENGTH,
+};
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>>,
+        ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    > FindexMultiMap<UserError, EntryTable, ChainTable>
+{
+    /// Writes the chains of the given entries of this multi-map into the given
+    /// `target`, using the new key and label. This multi-map is not modified.
+    ///
+    /// Deleted values, padding and missing links are not copied. The target
+    /// entries are written after their chains, and entries already present in
+    /// the target are skipped: if this operation is interrupted, running it
+    /// again completes the migration. The links written before the
+    /// interruption may be left unreferenced in the target Chain Table.
+    #[tracing::instrument(skip_all)]
+    pub async fn rebuild_into<
+        NewEntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>>,
+        NewChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    >(
+        &self,
+        rng: Arc<Mutex<impl CryptoRngCore>>,
+        key: &<Self as MmEnc<SEED_LENGTH, UserError>>::Key,
+        target: &FindexMultiMap<UserError, NewEntryTable, NewChainTable>,
+        new_key: &<FindexMultiMap<UserError, NewEntryTable, NewChainTable> as MmEnc<
+            SEED_LENGTH,
+            UserError,
+        >>::Key,
+        new_label: &Label,
+        tokens: HashSet<Token>,
+    ) -> Result<(), Error<UserError>> {
+        let entries = self
+            .fetch_entries(key, tokens)
+            .await?
+            .into_iter()
+            .map(|(_, entry)| {
+                let new_token =
+                    target
+                        .entry_table
+                        .tokenize(new_key, &entry.tag_hash, Some(new_label));
+                (new_token, entry)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let migrated_entries = target
+            .entry_table
+            .get(entries.keys().copied().collect())
+            .await?
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect::<HashSet<_>>();
+        debug!(
+            "rebuild_into: {} entries out of {} already migrated",
+            migrated_entries.len(),
+            entries.len()
+        );
+        let entries = entries
+            .into_iter()
+            .filter(|(token, _)| !migrated_entries.contains(token))
+            .collect::<HashMap<_, _>>();
+
+        let chain_metadata = entries
+            .iter()
+            .map(|(token, entry)| (*token, self.derive_metadata(entry)))
+            .collect::<HashMap<_, _>>();
+        let encrypted_links = self
+            .chain_table
+            .get(
+                chain_metadata
+                    .values()
+                    .flat_map(|(_, chain_tokens)| chain_tokens)
+                    .copied()
+                    .collect(),
+            )
+            .await?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let mut new_entries = HashMap::with_capacity(entries.len());
+        let mut new_links = HashMap::new();
+        for (new_token, entry) in entries {
+            let (chain_key, chain_tokens) = &chain_metadata[&new_token];
+            let links = chain_tokens
+                .iter()
+                .filter_map(|token| encrypted_links.get(token))
+                .map(|ciphertext| self.chain_table.resolve(chain_key, ciphertext).map(Link))
+                .collect::<Result<Vec<_>, _>>()?;
+            let values = self.recompose::<BLOCK_LENGTH, LINE_WIDTH>(&links)?;
+            let links = target.decompose::<BLOCK_LENGTH, LINE_WIDTH>(
+                &values
+                    .into_iter()
+                    .map(|v| (Operation::Addition, v))
+                    .collect::<Vec<_>>(),
+            )?;
+
+            let rng = &mut *rng.lock().expect("could not lock mutex");
+            let mut new_entry =
+                Entry::<NewChainTable>::new(target.chain_table.gen_seed(rng), entry.tag_hash, None);
+            let new_chain_key = target.chain_table.derive_keys(&new_entry.seed);
+            let new_chain_tokens =
+                target.derive_chain_tokens(&new_chain_key, entry.tag_hash.into(), links.len());
+            new_entry.chain_token = new_chain_tokens.last().copied();
+            for (token, link) in new_chain_tokens.into_iter().zip(links) {
+                new_links.insert(
+                    token,
+                    target.chain_table.prepare(rng, &new_chain_key, link.0)?,
+                );
+            }
+            new_entries.insert(
+                new_token,
+                target.entry_table.prepare(rng, new_key, new_entry.into())?,
+            );
+        }
+
+        target.chain_table.insert(new_links).await?;
+        target.entry_table.insert(new_entries).await
+    }
+}
@@ -5,9 +5,11 @@ use std::{
     fmt::{Debug, Display},
     hash::Hash,
     ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use base64::engine::{general_purpose::STANDARD, Engine};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use crate::{
     edx::{DxEnc, Token},
@@ -124,9 +126,25 @@ pub const LINK_LENGTH: usize = 1 + (BLOCK_LENGTH + 1) * LINE_WIDTH;
 /// The length byte is used to store the length of the data written into the
 /// block. The value `255` is used to mark the block as *non-terminating*. A
 /// non-terminating block can only be full.
+///
+/// Links hold plaintext blocks: they are zeroized on drop.
 #[derive(Debug)]
 pub struct Link(pub [u8; LINK_LENGTH]);
 
+impl Zeroize for Link {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for Link {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Link {}
+
 impl Link {
     /// Creates an empty Chain Table value.
     pub fn new() -> Self {
@@ -220,3 +238,95 @@ pub struct CompactingData<ChainTable: DxEnc<LINK_LENGTH>> {
     pub(crate) metadata: HashMap<Token, (ChainTable::Key, Vec<Token>)>,
     pub(crate) entries: HashMap<Token, Entry<ChainTable>>,
 }
+
+/// Entry Table line written by the commit loop, along with the value it
+/// replaced.
+#[derive(Debug)]
+pub(crate) struct CommittedEntry<EncryptedEntry> {
+    pub(crate) token: Token,
+    pub(crate) old_value: Option<EncryptedEntry>,
+    pub(crate) new_value: EncryptedEntry,
+}
+
+/// Counts the Entry Table modifications performed by the commit loop.
+#[derive(Debug, Default)]
+pub(crate) struct CommitCounters {
+    n_commits: AtomicUsize,
+    n_retries: AtomicUsize,
+}
+
+impl CommitCounters {
+    pub(crate) fn record(&self, n_commits: usize, n_retries: usize) {
+        self.n_commits.fetch_add(n_commits, Ordering::Relaxed);
+        self.n_retries.fetch_add(n_retries, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> CommitStats {
+        CommitStats {
+            n_commits: self.n_commits.load(Ordering::Relaxed),
+            n_retries: self.n_retries.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Statistics about the Entry Table modifications.
+///
+/// A retry is counted each time an entry upsert is rejected because another
+/// writer modified this entry concurrently. A high number of retries compared
+/// to the number of commits is the sign of hot keywords.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommitStats {
+    /// Number of entries successfully upserted.
+    pub n_commits: usize,
+    /// Number of entry upserts rejected and retried.
+    pub n_retries: usize,
+}
+
+/// Database accesses performed by a search.
+///
+/// Each iteration of the graph search fetches the entries of the keywords
+/// reached, then the links of their chains: it costs two round trips.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchPlan {
+    /// Number of fetches sent to the Entry Table and the Chain Table.
+    pub round_trips: usize,
+    /// Number of tokens fetched, whether a line is stored for them or not.
+    pub addresses_read: usize,
+    /// Total length of the chains read.
+    pub chain_length: usize,
+    /// Size of the lines returned, counted with their token.
+    pub bytes: usize,
+}
+
+/// Inconsistency found in the index by the integrity check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    /// The entry stored for this token cannot be decrypted.
+    UndecryptableEntry(Token),
+    /// The link stored for this token is referenced by the chain of the given
+    /// entry but is not stored.
+    MissingLink { entry: Token, link: Token },
+    /// The link stored for this token cannot be decrypted using the chain key
+    /// of the given entry.
+    UndecryptableLink { entry: Token, link: Token },
+    /// The values stored in the chain of the given entry cannot be recomposed.
+    MalformedChain(Token),
+    /// The link stored for this token is not referenced by any entry.
+    OrphanLink(Token),
+}
+
+impl Display for IntegrityIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UndecryptableEntry(token) => write!(f, "undecryptable entry {token}"),
+            Self::MissingLink { entry, link } => {
+                write!(f, "missing link {link} in the chain of entry {entry}")
+            }
+            Self::UndecryptableLink { entry, link } => {
+                write!(f, "undecryptable link {link} in the chain of entry {entry}")
+            }
+            Self::MalformedChain(token) => write!(f, "malformed chain for entry {token}"),
+            Self::OrphanLink(token) => write!(f, "orphan link {token}"),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Synchronous facade over the `Index` interface of `Findex`.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    future::Future,
+};
+
+use futures_executor::LocalPool;
+
+use crate::{
+    edx::TokenDump,
+    findex_mm::{ENTRY_LENGTH, LINK_LENGTH},
+    Data, DbInterfaceErrorTrait, DxEnc, Error, Findex, Index, IndexedValue,
+    IndexedValueToKeywordsMap, Keyword, KeywordToDataMap, Keywords, Label, UserKey,
+};
+
+/// Findex handle exposing the [`Index`] operations as blocking methods.
+///
+/// Each call is run to completion on an executor created along with the
+/// handle and reused by all calls. This executor does not provide any I/O
+/// reactor: database implementations relying on a specific runtime (e.g.
+/// Tokio) must be called from that runtime instead.
+#[derive(Debug)]
+pub struct BlockingFindex<
+    UserError: DbInterfaceErrorTrait,
+    EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>>,
+    ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+> {
+    findex: Findex<UserError, EntryTable, ChainTable>,
+    executor: RefCell<LocalPool>,
+}
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+        ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    > BlockingFindex<UserError, EntryTable, ChainTable>
+{
+    /// Wraps the given index.
+    pub fn new(findex: Findex<UserError, EntryTable, ChainTable>) -> Self {
+        Self {
+            findex,
+            executor: RefCell::new(LocalPool::new()),
+        }
+    }
+
+    /// Returns the wrapped index.
+    pub fn into_inner(self) -> Findex<UserError, EntryTable, ChainTable> {
+        self.findex
+    }
+
+    fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+        self.executor.borrow_mut().run_until(future)
+    }
+
+    /// Generates a new random cryptographic key.
+    ///
+    /// See [`Index::keygen()`].
+    pub fn keygen(&self) -> UserKey {
+        self.findex.keygen()
+    }
+
+    /// Searches the index for the given keywords.
+    ///
+    /// See [`Index::search()`].
+    pub fn search<
+        F: Future<Output = Result<bool, String>>,
+        Interrupt: Fn(HashMap<Keyword, HashSet<IndexedValue<Keyword, Data>>>) -> F,
+    >(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        keywords: Keywords,
+        interrupt: &Interrupt,
+    ) -> Result<KeywordToDataMap, Error<UserError>> {
+        self.block_on(self.findex.search(key, label, keywords, interrupt))
+    }
+
+    /// Adds the given associations to the index.
+    ///
+    /// See [`Index::add()`].
+    pub fn add(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        associations: IndexedValueToKeywordsMap,
+    ) -> Result<Keywords, Error<UserError>> {
+        self.block_on(self.findex.add(key, label, associations))
+    }
+
+    /// Removes the given associations from the index.
+    ///
+    /// See [`Index::delete()`].
+    pub fn delete(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        associations: IndexedValueToKeywordsMap,
+    ) -> Result<Keywords, Error<UserError>> {
+        self.block_on(self.findex.delete(key, label, associations))
+    }
+
+    /// Compacts a portion of the index.
+    ///
+    /// See [`Index::compact()`].
+    pub fn compact<
+        F: Future<Output = Result<HashSet<Data>, String>>,
+        Filter: Fn(HashSet<Data>) -> F,
+    >(
+        &self,
+        old_key: &UserKey,
+        new_key: &UserKey,
+        old_label: &Label,
+        new_label: &Label,
+        compacting_rate: f64,
+        data_filter: &Filter,
+    ) -> Result<(), Error<UserError>> {
+        self.block_on(self.findex.compact(
+            old_key,
+            new_key,
+            old_label,
+            new_label,
+            compacting_rate,
+            data_filter,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edx::{chain_table::ChainTable, entry_table::EntryTable, in_memory::InMemoryDb};
+
+    #[test]
+    fn test_blocking_findex() {
+        let findex = BlockingFindex::new(Findex::new(
+            EntryTable::setup(InMemoryDb::default()),
+            ChainTable::setup(InMemoryDb::default()),
+        ));
+        let key = findex.keygen();
+        let label = Label::from("label");
+        let keyword = Keyword::from("keyword");
+        let associations = |i: usize| {
+            IndexedValueToKeywordsMap::from_iter([(
+                IndexedValue::Data(Data::from(format!("location {i}").as_str())),
+                Keywords::from_iter([keyword.clone()]),
+            )])
+        };
+
+        for i in 0..2 {
+            findex.add(&key, &label, associations(i)).unwrap();
+        }
+        findex.delete(&key, &label, associations(0)).unwrap();
+
+        let res = findex
+            .search(
+                &key,
+                &label,
+                Keywords::from_iter([keyword.clone()]),
+                &|_| async { Ok(false) },
+            )
+            .unwrap();
+        assert_eq!(
+            res.get(&keyword),
+            Some(&HashSet::from_iter([Data::from("location 1")]))
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Bloom filter of the keywords indexed by a `Findex` index.
+//!
+//! The filter is built over the Entry Table tokens, which are already known
+//! to the database: it can be sent to clients without leaking the indexed
+//! keywords. A client holding the key and the label can then check whether
+//! a keyword may be indexed without querying the database.
+
+use std::f64::consts::LN_2;
+
+use tiny_keccak::{Hasher, Sha3};
+
+use crate::{
+    edx::{Token, TokenDump},
+    findex_mm::{ENTRY_LENGTH, LINK_LENGTH},
+    parameters::HASH_LENGTH,
+    CoreError, DbInterfaceErrorTrait, DxEnc, Error, Findex, Keyword, Label, UserKey,
+};
+
+/// Returns the indexes of the filter bits associated to the given token.
+///
+/// Tokens are pseudo-random, so their bytes are directly used as the two
+/// hashes of the double hashing scheme.
+fn bit_indexes(token: &Token, n_hashes: u8, n_bits: usize) -> impl Iterator<Item = usize> {
+    let mut h1 = [0; 8];
+    let mut h2 = [0; 8];
+    h1.copy_from_slice(&token[..8]);
+    h2.copy_from_slice(&token[8..16]);
+    let h1 = u64::from_le_bytes(h1);
+    let h2 = u64::from_le_bytes(h2) | 1;
+    (0..u64::from(n_hashes)).map(move |i| {
+        // The modulo of a `usize` fits in a `usize`.
+        #[allow(clippy::cast_possible_truncation)]
+        let index = (h1.wrapping_add(i.wrapping_mul(h2)) % n_bits as u64) as usize;
+        index
+    })
+}
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+        ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    > Findex<UserError, EntryTable, ChainTable>
+{
+    /// Builds a Bloom filter of the Entry Table tokens with the given
+    /// false-positive rate.
+    ///
+    /// The filter is serialized as the number of hash functions, on one byte,
+    /// followed by the bits of the filter. Its size is about
+    /// `-1.44 * log2(fp_rate)` bits per indexed keyword. It is only valid
+    /// until the next addition of a new keyword or the next compaction.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the false-positive rate is not in `]0, 1[`.
+    pub async fn export_bloom(&self, fp_rate: f64) -> Result<Vec<u8>, Error<UserError>> {
+        if !(0.0 < fp_rate && fp_rate < 1.0) {
+            return Err(Error::Conversion(format!(
+                "false-positive rate must be in ]0, 1[, got {fp_rate}"
+            )));
+        }
+        let tokens = self
+            .findex_graph
+            .findex_mm
+            .entry_table
+            .dump_tokens()
+            .await?;
+
+        // Optimal parameters: m = -n * ln(p) / ln(2)^2 and k = m / n * ln(2).
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss
+        )]
+        let (n_bytes, n_hashes) = {
+            let n = tokens.len().max(1) as f64;
+            let n_bits = (-n * fp_rate.ln() / (LN_2 * LN_2)).ceil();
+            let n_hashes = (n_bits / n * LN_2).round().clamp(1.0, f64::from(u8::MAX));
+            ((n_bits / 8.0).ceil() as usize, n_hashes as u8)
+        };
+
+        let mut filter = vec![0; 1 + n_bytes];
+        filter[0] = n_hashes;
+        for token in &tokens {
+            for i in bit_indexes(token, n_hashes, 8 * n_bytes) {
+                filter[1 + i / 8] |= 1 << (i % 8);
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Checks whether the given keyword may be indexed under the given key
+    /// and label, using a filter built by [`Self::export_bloom`].
+    ///
+    /// The database is not queried. A `false` means the keyword is not
+    /// indexed: searching it would return no result. A `true` may be a false
+    /// positive.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the filter is malformed.
+    pub fn bloom_contains(
+        &self,
+        filter: &[u8],
+        key: &UserKey,
+        label: &Label,
+        keyword: &Keyword,
+    ) -> Result<bool, Error<UserError>> {
+        let (&n_hashes, bits) = filter
+            .split_first()
+            .filter(|(&n_hashes, bits)| 0 < n_hashes && !bits.is_empty())
+            .ok_or_else(|| CoreError::Conversion("malformed Bloom filter".to_string()))?;
+
+        let mut tag_hash = [0; HASH_LENGTH];
+        let mut hasher = Sha3::v256();
+        hasher.update(keyword.as_ref());
+        hasher.finalize(&mut tag_hash);
+        let key = self.derive_graph_key(key);
+        let token = self
+            .findex_graph
+            .findex_mm
+            .entry_table
+            .tokenize(&key, &tag_hash, Some(label));
+
+        Ok(
+            bit_indexes(&token, n_hashes, 8 * bits.len())
+                .all(|i| bits[i / 8] & (1 << (i % 8)) != 0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        edx::{chain_table::ChainTable, entry_table::EntryTable, in_memory::InMemoryDb},
+        Data, DxEnc, Error, Findex, Index, IndexedValue, IndexedValueToKeywordsMap, Keyword,
+        Keywords, Label,
+    };
+
+    #[actix_rt::test]
+    async fn test_bloom_filter() {
+        let findex = Findex::new(
+            EntryTable::setup(InMemoryDb::default()),
+            ChainTable::setup(InMemoryDb::default()),
+        );
+        let key = findex.keygen();
+        let label = Label::from("label");
+        let n_keywords = 1_000;
+        findex
+            .add(
+                &key,
+                &label,
+                IndexedValueToKeywordsMap::from_iter([(
+                    IndexedValue::Data(Data::from("location")),
+                    (0..n_keywords)
+                        .map(|i| Keyword::from(format!("keyword {i}").as_str()))
+                        .collect::<Keywords>(),
+                )]),
+            )
+            .await
+            .unwrap();
+
+        let fp_rate = 0.01;
+        let filter = findex.export_bloom(fp_rate).await.unwrap();
+
+        // No false negative.
+        for i in 0..n_keywords {
+            let keyword = Keyword::from(format!("keyword {i}").as_str());
+            assert!(findex
+                .bloom_contains(&filter, &key, &label, &keyword)
+                .unwrap());
+        }
+
+        // The false-positive rate is close to the target.
+        let n_trials = 10_000;
+        let mut n_false_positives = 0;
+        for i in 0..n_trials {
+            let keyword = Keyword::from(format!("absent keyword {i}").as_str());
+            if findex
+                .bloom_contains(&filter, &key, &label, &keyword)
+                .unwrap()
+            {
+                n_false_positives += 1;
+            }
+        }
+        assert!(
+            n_false_positives < 2 * n_trials / 100,
+            "{n_false_positives}"
+        );
+
+        // Indexed keywords are not found with another label.
+        let other_label = Label::from("other label");
+        let n_found = (0..n_keywords)
+            .filter(|i| {
+                findex
+                    .bloom_contains(
+                        &filter,
+                        &key,
+                        &other_label,
+                        &Keyword::from(format!("keyword {i}").as_str()),
+                    )
+                    .unwrap()
+            })
+            .count();
+        assert!(n_found < 2 * n_keywords / 100, "{n_found}");
+
+        assert!(matches!(
+            findex.export_bloom(1.0).await,
+            Err(Error::Conversion(_))
+        ));
+        assert!(matches!(
+            findex.bloom_contains(&[], &key, &label, &Keyword::from("keyword 0")),
+            Err(Error::Conversion(_))
+        ));
+    }
+}
@@ -0,0 +1,177 @@
+//! JSON export and import of the tables of a `Findex` index.
+
+use std::collections::HashMap;
+
+use base64::engine::{general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+
+use crate::{
+    edx::TokenDump,
+    findex_mm::{ENTRY_LENGTH, LINK_LENGTH},
+    CoreError, DbInterfaceErrorTrait, DxEnc, EncryptedValue, Error, Findex, Token,
+};
+
+/// Returns the JSON array of the base64 encoded lines of the given table.
+async fn export_table<const VALUE_LENGTH: usize, UserError: DbInterfaceErrorTrait>(
+    table: &(impl DxEnc<
+        VALUE_LENGTH,
+        Error = Error<UserError>,
+        EncryptedValue = EncryptedValue<VALUE_LENGTH>,
+    > + TokenDump<Error = Error<UserError>>),
+) -> Result<Value, Error<UserError>> {
+    let lines = table.get(table.dump_tokens().await?).await?;
+    Ok(Value::Array(
+        lines
+            .iter()
+            .map(|(token, value)| {
+                json!([STANDARD.encode(&**token), STANDARD.encode(Vec::from(value))])
+            })
+            .collect(),
+    ))
+}
+
+/// Parses the lines of the given JSON array.
+fn parse_table<const VALUE_LENGTH: usize>(
+    table: Option<&Value>,
+) -> Result<HashMap<Token, EncryptedValue<VALUE_LENGTH>>, CoreError> {
+    let decode = |value: &Value| {
+        value
+            .as_str()
+            .ok_or_else(|| CoreError::Conversion(format!("string expected, got {value}")))
+            .and_then(|value| {
+                STANDARD
+                    .decode(value)
+                    .map_err(|e| CoreError::Conversion(e.to_string()))
+            })
+    };
+    table
+        .and_then(Value::as_array)
+        .ok_or_else(|| CoreError::Conversion("missing table".to_string()))?
+        .iter()
+        .map(|line| match line.as_array().map(Vec::as_slice) {
+            Some([token, value]) => Ok((
+                Token::try_from(decode(token)?.as_slice())?,
+                EncryptedValue::try_from(decode(value)?.as_slice())?,
+            )),
+            _ => Err(CoreError::Conversion(format!(
+                "[token, value] pair expected, got {line}"
+            ))),
+        })
+        .collect()
+}
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<
+                ENTRY_LENGTH,
+                Error = Error<UserError>,
+                EncryptedValue = EncryptedValue<ENTRY_LENGTH>,
+            > + TokenDump<Error = Error<UserError>>,
+        ChainTable: DxEnc<
+                LINK_LENGTH,
+                Error = Error<UserError>,
+                EncryptedValue = EncryptedValue<LINK_LENGTH>,
+            > + TokenDump<Error = Error<UserError>>,
+    > Findex<UserError, EntryTable, ChainTable>
+{
+    /// Exports all the lines of the Entry Table and the Chain Table as a JSON
+    /// object:
+    ///
+    /// ```json
+    /// {
+    ///     "entry_table": [[token, value], ...],
+    ///     "chain_table": [[token, value], ...]
+    /// }
+    /// ```
+    ///
+    /// Tokens and values are base64 encoded. Values are encrypted: the export
+    /// does not leak more than the database itself. The export is only
+    /// consistent if no modification is performed concurrently.
+    pub async fn export_json(&self) -> Result<String, Error<UserError>> {
+        let findex_mm = &self.findex_graph.findex_mm;
+        Ok(json!({
+            "entry_table": export_table(&findex_mm.entry_table).await?,
+            "chain_table": export_table(&findex_mm.chain_table).await?,
+        })
+        .to_string())
+    }
+
+    /// Inserts the lines of the given JSON export into the tables of this
+    /// index. Searches then return the results of the exported index, using
+    /// the same key and label.
+    ///
+    /// # Error
+    ///
+    /// Returns an error without inserting anything if the export is malformed.
+    pub async fn import_json(&self, export: &str) -> Result<(), Error<UserError>> {
+        let export = serde_json::from_str::<Value>(export)
+            .map_err(|e| CoreError::Conversion(format!("cannot parse JSON export: {e}")))?;
+        let entries = parse_table(export.get("entry_table"))?;
+        let links = parse_table(export.get("chain_table"))?;
+        let findex_mm = &self.findex_graph.findex_mm;
+        findex_mm.chain_table.insert(links).await?;
+        findex_mm.entry_table.insert(entries).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        edx::{chain_table::ChainTable, entry_table::EntryTable, in_memory::InMemoryDb},
+        Data, DxEnc, Error, Findex, Index, IndexedValue, IndexedValueToKeywordsMap, Keyword,
+        Keywords, Label,
+    };
+
+    #[actix_rt::test]
+    async fn test_json_export() {
+        let findex = Findex::new(
+            EntryTable::setup(InMemoryDb::default()),
+            ChainTable::setup(InMemoryDb::default()),
+        );
+        let key = findex.keygen();
+        let label = Label::from("label");
+        let keywords =
+            Keywords::from_iter([Keyword::from("keyword 1"), Keyword::from("keyword 2")]);
+        findex
+            .add(
+                &key,
+                &label,
+                IndexedValueToKeywordsMap::from_iter([(
+                    IndexedValue::Data(Data::from("location")),
+                    keywords.clone(),
+                )]),
+            )
+            .await
+            .unwrap();
+
+        let export = findex.export_json().await.unwrap();
+
+        let new_findex = Findex::new(
+            EntryTable::setup(InMemoryDb::default()),
+            ChainTable::setup(InMemoryDb::default()),
+        );
+        new_findex.import_json(&export).await.unwrap();
+        assert_eq!(export.len(), new_findex.export_json().await.unwrap().len());
+
+        let res = findex
+            .search(&key, &label, keywords.clone(), &|_| async { Ok(false) })
+            .await
+            .unwrap();
+        let new_res = new_findex
+            .search(&key, &label, keywords.clone(), &|_| async { Ok(false) })
+            .await
+            .unwrap();
+        assert_eq!(res, new_res);
+        assert_eq!(
+            new_res.get(&Keyword::from("keyword 1")),
+            Some(&HashSet::from_iter([Data::from("location")]))
+        );
+
+        let res = new_findex
+            .import_json(r#"{"entry_table": [["AA==", ""]]}"#)
+            .await;
+        assert!(matches!(res, Err(Error::Conversion(_))), "{res:?}");
+    }
+}
@@ -2,30 +2,44 @@
 //! details.
 
 use std::{
+    cell::{Cell, RefCell},
     collections::{HashMap, HashSet},
     fmt::Debug,
     future::Future,
     sync::{Arc, Mutex},
+    time::Instant,
 };
 
 use async_trait::async_trait;
 use tracing::{instrument, trace};
 
 use crate::{
-    edx::{Token, TokenDump, Tokens},
+    edx::{line_length, DbSize, Token, TokenDump, Tokens},
     findex_graph::{FindexGraph, GxEnc},
-    findex_mm::{Operation, ENTRY_LENGTH, LINK_LENGTH},
-    DbInterfaceErrorTrait, DxEnc, Error, IndexedValue,
+    findex_mm::{
+        CommitStats, IntegrityIssue, MmEnc, Operation, SearchPlan, ENTRY_LENGTH, LINK_LENGTH,
+    },
+    CoreError, DbInterfaceErrorTrait, DxEnc, Error, IndexedValue,
 };
 
+#[cfg(feature = "blocking")]
+mod blocking;
+mod bloom;
+#[cfg(feature = "json")]
+mod json;
+mod read_only;
 mod structs;
 
+#[cfg(feature = "blocking")]
+pub use blocking::BlockingFindex;
 use cosmian_crypto_core::{
     reexport::rand_core::{RngCore, SeedableRng},
     CsRng, RandomFixedSizeCBytes,
 };
+pub use read_only::ReadOnlyFindex;
 pub use structs::{
-    Data, IndexedValueToKeywordsMap, Keyword, KeywordToDataMap, Keywords, Label, UserKey,
+    Data, FindexStats, IndexedValueToKeywordsMap, Keyword, KeywordToDataMap, Keywords, Label,
+    UserKey,
 };
 
 /// User-friendly interface to the Findex algorithm.
@@ -112,6 +126,23 @@ pub trait Index<EntryTable: DxEnc<ENTRY_LENGTH>, ChainTable: DxEnc<LINK_LENGTH>>
     ) -> Result<(), Self::Error>;
 }
 
+/// Checks the given keyword can be indexed or searched.
+fn check_keyword(keyword: &Keyword) -> Result<(), CoreError> {
+    if keyword.is_empty() {
+        Err(Error::EmptyKeyword)
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks the given value can be indexed. Data of any length can be indexed.
+fn check_value(value: &IndexedValue<Keyword, Data>) -> Result<(), CoreError> {
+    match value {
+        IndexedValue::Pointer(keyword) => check_keyword(keyword),
+        IndexedValue::Data(_) => Ok(()),
+    }
+}
+
 /// Findex type implements the Findex algorithm.
 #[derive(Debug)]
 pub struct Findex<
@@ -156,6 +187,7 @@ impl<
     ) -> Result<KeywordToDataMap, Self::Error> {
         trace!("search: entering: label: {label}");
         trace!("search: entering: keywords: {keywords}");
+        keywords.iter().try_for_each(check_keyword)?;
         // TODO: avoid this copy
         let mut seed =
             <FindexGraph<UserError, EntryTable, ChainTable> as GxEnc<UserError>>::Seed::default();
@@ -195,7 +227,9 @@ impl<
 
         let mut modifications = HashMap::<_, Vec<_>>::new();
         for (value, keywords) in additions {
+            check_value(&value)?;
             for keyword in keywords {
+                check_keyword(&keyword)?;
                 modifications
                     .entry(keyword)
                     .or_default()
@@ -227,7 +261,9 @@ impl<
 
         let mut modifications = HashMap::<_, Vec<_>>::new();
         for (value, keywords) in deletions {
+            check_value(&value)?;
             for keyword in keywords {
+                check_keyword(&keyword)?;
                 modifications
                     .entry(keyword)
                     .or_default()
@@ -290,7 +326,7 @@ impl<
         old_seed.as_mut().copy_from_slice(old_key);
         let old_key = self.findex_graph.derive_keys(&old_seed);
 
-        let entry_tokens = self.findex_graph.list_indexed_encrypted_tags().await?;
+        let entry_tokens = self.dump_tokens().await?.into_iter().collect::<Vec<_>>();
 
         let entries_to_compact = self
             .select_random_tokens(
@@ -346,6 +382,397 @@ impl<
     /// N * 32 + BS * EB + f * BS * LB
     const COMPACT_BATCH_SIZE: usize = 1_000_000;
 
+    /// Instantiates a new index using the given RNG instead of one seeded from
+    /// the system entropy.
+    ///
+    /// This is only meant to produce reproducible test vectors: given the same
+    /// seed and the same operations, the same values are written to the
+    /// tables. Since associations are processed in an unspecified order, this
+    /// only holds for operations indexing a single association at once.
+    pub fn new_with_rng(et: EntryTable, ct: ChainTable, rng: CsRng) -> Self {
+        Self {
+            findex_graph: FindexGraph::new(et, ct),
+            rng: Arc::new(Mutex::new(rng)),
+        }
+    }
+
+    /// Instantiates a new index after checking both tables can be queried.
+    ///
+    /// Contrary to [`new()`](Index::new), which does not call the databases,
+    /// this fetches a random token from each table so that an unreachable
+    /// database is reported at startup instead of upon the first operation.
+    pub async fn connect(et: EntryTable, ct: ChainTable) -> Result<Self, Error<UserError>> {
+        let findex = Self::new(et, ct);
+        let mut token = [0; Token::LENGTH];
+        findex
+            .rng
+            .lock()
+            .expect("could not lock mutex")
+            .fill_bytes(&mut token);
+        let findex_mm = &findex.findex_graph.findex_mm;
+        findex_mm
+            .entry_table
+            .get(HashSet::from_iter([Token::from(token)]))
+            .await?;
+        findex_mm
+            .chain_table
+            .get(HashSet::from_iter([Token::from(token)]))
+            .await?;
+        Ok(findex)
+    }
+
+    /// Re-encrypts the Entry Table using the `new_key` and the `new_label`
+    /// without compacting any chain.
+    ///
+    /// Chain keys are derived from the seeds stored in the entries, thus only
+    /// the entries need to be re-encrypted and the Chain Table is left
+    /// untouched. Entries are processed by batches of
+    /// [`COMPACT_BATCH_SIZE`](Self::COMPACT_BATCH_SIZE).
+    ///
+    /// Searches should only switch to the new key and label once this
+    /// operation has returned: entries that have not been re-encrypted yet
+    /// cannot be found using the new key, and re-encrypted entries cannot be
+    /// found using the old one.
+    pub async fn rotate_key(
+        &self,
+        old_key: &UserKey,
+        new_key: &UserKey,
+        old_label: &Label,
+        new_label: &Label,
+    ) -> Result<(), Error<UserError>> {
+        self.compact(
+            old_key,
+            new_key,
+            old_label,
+            new_label,
+            0f64,
+            &|data| async { Ok(data) },
+        )
+        .await
+    }
+
+    /// Returns `true` if the given keyword indexes at least one value.
+    ///
+    /// Only the chain associated to this keyword is fetched: pointers are not
+    /// followed. If this keyword is not indexed, only the Entry Table is
+    /// queried. As for search results, a keyword whose associations have all
+    /// been deleted is considered as not indexed.
+    pub async fn contains_keyword(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        keyword: &Keyword,
+    ) -> Result<bool, Error<UserError>> {
+        check_keyword(keyword)?;
+        let key = self.derive_graph_key(key);
+        let res = self
+            .findex_graph
+            .findex_mm
+            .get(&key, HashSet::from_iter([keyword.clone()]), label)
+            .await?;
+        Ok(res.get(keyword).is_some_and(|values| !values.is_empty()))
+    }
+
+    /// Searches the given keyword and returns the database accesses performed
+    /// instead of the results, e.g. to diagnose slow searches.
+    ///
+    /// Pointers are followed as by [`search()`](Index::search), one graph
+    /// level per iteration.
+    pub async fn explain_search(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        keyword: &Keyword,
+    ) -> Result<SearchPlan, Error<UserError>> {
+        check_keyword(keyword)?;
+        let key = self.derive_graph_key(key);
+        let mut plan = SearchPlan::default();
+        let mut reached = HashSet::new();
+        let mut keywords = HashSet::from_iter([keyword.clone()]);
+        while !keywords.is_empty() {
+            reached.extend(keywords.iter().cloned());
+            let res = self
+                .findex_graph
+                .findex_mm
+                .get_with_plan(&key, keywords, label, &mut plan)
+                .await?;
+            keywords = HashSet::new();
+            for value in res.into_values().flatten() {
+                if let IndexedValue::Pointer(child) =
+                    IndexedValue::<Keyword, Data>::try_from(value.as_slice())?
+                {
+                    if !reached.contains(&child) {
+                        keywords.insert(child);
+                    }
+                }
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Searches the given keyword and returns at most `limit` of the data
+    /// matching the given predicate.
+    ///
+    /// The whole graph of this keyword is still fetched and decrypted since
+    /// a value may be deleted by a later link: only the collection of the
+    /// results is shortened.
+    pub async fn search_filter(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        keyword: &Keyword,
+        predicate: impl Fn(&Data) -> bool,
+        limit: usize,
+    ) -> Result<HashSet<Data>, Error<UserError>> {
+        let mut res = self
+            .search(
+                key,
+                label,
+                Keywords::from_iter([keyword.clone()]),
+                &|_| async { Ok(false) },
+            )
+            .await?;
+        Ok(res
+            .remove(keyword)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(predicate)
+            .take(limit)
+            .collect())
+    }
+
+    /// Searches the given keyword, stopping the graph traversal once the
+    /// given deadline has passed.
+    ///
+    /// The deadline is checked after each graph search iteration, which does
+    /// not interrupt an ongoing database call. Returns the data found along
+    /// with `false` if some pointers were left unexplored, in which case the
+    /// data they lead to is missing from the results.
+    pub async fn search_with_deadline(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        keyword: &Keyword,
+        deadline: Instant,
+    ) -> Result<(HashSet<Data>, bool), Error<UserError>> {
+        let reached = RefCell::new(HashSet::new());
+        let is_complete = Cell::new(true);
+        let interrupt = |results: HashMap<Keyword, HashSet<IndexedValue<Keyword, Data>>>| {
+            let mut reached = reached.borrow_mut();
+            reached.extend(results.keys().cloned());
+            let has_pending_pointers = results.values().flatten().any(
+                |value| matches!(value, IndexedValue::Pointer(child) if !reached.contains(child)),
+            );
+            let is_interrupted = has_pending_pointers && deadline <= Instant::now();
+            if is_interrupted {
+                is_complete.set(false);
+            }
+            async move { Ok(is_interrupted) }
+        };
+        let mut res = self
+            .search(
+                key,
+                label,
+                Keywords::from_iter([keyword.clone()]),
+                &interrupt,
+            )
+            .await?;
+        Ok((res.remove(keyword).unwrap_or_default(), is_complete.get()))
+    }
+
+    /// Removes all the associations of the given keyword, including the
+    /// pointers to other keywords. Other keywords are not modified.
+    ///
+    /// The values currently indexed are fetched and deleted in one addition
+    /// to the chain of this keyword, which is conditionally committed as any
+    /// other modification. Values added concurrently after the fetch are not
+    /// deleted. The chain is reclaimed by the next compaction.
+    ///
+    /// Returns `false` if this keyword did not index any value.
+    pub async fn delete_keyword(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        keyword: &Keyword,
+    ) -> Result<bool, Error<UserError>> {
+        check_keyword(keyword)?;
+        let key = self.derive_graph_key(key);
+        let mut res = self
+            .findex_graph
+            .findex_mm
+            .get(&key, HashSet::from_iter([keyword.clone()]), label)
+            .await?;
+        let values = res.remove(keyword).unwrap_or_default();
+        if values.is_empty() {
+            return Ok(false);
+        }
+        let deletions = values
+            .into_iter()
+            .map(|value| (Operation::Deletion, value))
+            .collect();
+        self.findex_graph
+            .findex_mm
+            .insert(
+                self.rng.clone(),
+                &key,
+                HashMap::from_iter([(keyword.clone(), deletions)]),
+                label,
+            )
+            .await?;
+        Ok(true)
+    }
+
+    /// Compacts the chain of the given keyword only, keeping the same key and
+    /// label.
+    ///
+    /// Deleted associations and internal padding are removed from this chain.
+    /// Contrary to [`compact()`](Index::compact), this operation can be run
+    /// concurrently with additions. Returns an [`Error::MissingLinks`] if
+    /// some links of this chain are still missing after a few reads, e.g.
+    /// because an addition is still writing them: the chain is then left
+    /// unchanged and the compaction can be run again later. Does nothing if
+    /// the given keyword is not indexed.
+    pub async fn compact_keyword(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        keyword: &Keyword,
+    ) -> Result<(), Error<UserError>> {
+        check_keyword(keyword)?;
+        let key = self.derive_graph_key(key);
+        self.findex_graph
+            .findex_mm
+            .compact_tag(self.rng.clone(), &key, keyword, label)
+            .await
+    }
+
+    /// Copies this index into the given tables, using the new key and label,
+    /// and returns an index handle on these tables. This index is not
+    /// modified.
+    ///
+    /// Deleted associations are not copied. The Entry Table is processed by
+    /// batches of [`COMPACT_BATCH_SIZE`](Self::COMPACT_BATCH_SIZE) entries. If
+    /// this operation is interrupted, calling it again with the same
+    /// parameters completes the migration without duplicating the keywords
+    /// already copied.
+    pub async fn rebuild<
+        NewEntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+        NewChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    >(
+        &self,
+        key: &UserKey,
+        new_key: &UserKey,
+        new_label: &Label,
+        entry_table: NewEntryTable,
+        chain_table: NewChainTable,
+    ) -> Result<Findex<UserError, NewEntryTable, NewChainTable>, Error<UserError>> {
+        let new_findex = Findex {
+            findex_graph: FindexGraph::new(entry_table, chain_table),
+            rng: self.rng.clone(),
+        };
+        let key = self.derive_graph_key(key);
+        let new_key = new_findex.derive_graph_key(new_key);
+        let tokens = self
+            .findex_graph
+            .findex_mm
+            .entry_table
+            .dump_tokens()
+            .await?
+            .into_iter()
+            .collect::<Vec<_>>();
+        for batch in tokens.chunks(Self::COMPACT_BATCH_SIZE) {
+            self.findex_graph
+                .findex_mm
+                .rebuild_into(
+                    self.rng.clone(),
+                    &key,
+                    &new_findex.findex_graph.findex_mm,
+                    &new_key,
+                    new_label,
+                    batch.iter().copied().collect(),
+                )
+                .await?;
+        }
+        Ok(new_findex)
+    }
+
+    /// Adds all the associations of the `other` index to this index, using
+    /// the given label. Both indexes must use the given key. The `other`
+    /// index is not modified.
+    ///
+    /// Associations are copied whatever the label used to index them in the
+    /// `other` index. Its Entry Table is processed by batches of
+    /// [`COMPACT_BATCH_SIZE`](Self::COMPACT_BATCH_SIZE) entries. Associations
+    /// already indexed by this index are not added again.
+    pub async fn merge<
+        OtherEntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+        OtherChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    >(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        other: &Findex<UserError, OtherEntryTable, OtherChainTable>,
+    ) -> Result<(), Error<UserError>> {
+        let other_key = other.derive_graph_key(key);
+        let key = self.derive_graph_key(key);
+        let tokens = other
+            .findex_graph
+            .findex_mm
+            .entry_table
+            .dump_tokens()
+            .await?
+            .into_iter()
+            .collect::<Vec<_>>();
+        for batch in tokens.chunks(Self::COMPACT_BATCH_SIZE) {
+            self.findex_graph
+                .findex_mm
+                .merge_from(
+                    self.rng.clone(),
+                    &key,
+                    label,
+                    &other.findex_graph.findex_mm,
+                    &other_key,
+                    batch.iter().copied().collect(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the tokens of all the entries stored in the Entry Table, i.e.
+    /// one token per indexed keyword.
+    ///
+    /// These tokens are encrypted: they can neither be linked back to the
+    /// keywords nor used to fetch the indexed values without the key and the
+    /// label used to index them. Their number is the number of indexed
+    /// keywords, which is already known to the database.
+    pub async fn dump_tokens(&self) -> Result<Tokens, Error<UserError>> {
+        Ok(self
+            .findex_graph
+            .list_indexed_encrypted_tags()
+            .await?
+            .into_iter()
+            .collect())
+    }
+
+    /// Returns the statistics about the Entry Table modifications performed
+    /// by this index. Rejected upserts due to concurrent additions to the same
+    /// keywords are counted as retries.
+    pub fn commit_stats(&self) -> CommitStats {
+        self.findex_graph.findex_mm.commit_stats()
+    }
+
+    /// Derives the Findex Graph key from the given user key.
+    fn derive_graph_key(
+        &self,
+        key: &UserKey,
+    ) -> <FindexGraph<UserError, EntryTable, ChainTable> as GxEnc<UserError>>::Key {
+        let mut seed =
+            <FindexGraph<UserError, EntryTable, ChainTable> as GxEnc<UserError>>::Seed::default();
+        seed.as_mut().copy_from_slice(key.as_bytes());
+        self.findex_graph.derive_keys(&seed)
+    }
+
     /// Draw `n` tokens at random among the given `tokens`. The same token may
     /// be drawn several times, thus the number of tokens returned may be
     /// lower than `n`.
@@ -414,6 +841,7 @@ impl<
             .await
             .map_err(<Self as Index<EntryTable, ChainTable>>::Error::Filter)?;
 
+        #[allow(clippy::unnecessary_map_or)]
         let remaining_values = indexed_values
             .into_iter()
             .map(|(entry_token, associated_values)| {
@@ -435,3 +863,170 @@ impl<
             .await
     }
 }
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + DbSize<Error = Error<UserError>>,
+        ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>> + DbSize<Error = Error<UserError>>,
+    > Findex<UserError, EntryTable, ChainTable>
+{
+    /// Returns the size of the index, as counted by the databases.
+    pub async fn stats(&self) -> Result<FindexStats, Error<UserError>> {
+        let n_keywords = DbSize::len(&self.findex_graph.findex_mm.entry_table).await?;
+        let n_links = DbSize::len(&self.findex_graph.findex_mm.chain_table).await?;
+        Ok(FindexStats {
+            n_keywords,
+            n_links,
+            size: n_keywords * line_length::<ENTRY_LENGTH>()
+                + n_links * line_length::<LINK_LENGTH>(),
+        })
+    }
+}
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+        ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+    > Findex<UserError, EntryTable, ChainTable>
+{
+    /// Reads the entire index and reports the inconsistencies found, without
+    /// modifying anything.
+    ///
+    /// All entries should be decrypted by the given key, whatever label was
+    /// used to index them. Additions performed concurrently to this check may
+    /// be reported as missing links.
+    pub async fn verify_integrity(
+        &self,
+        key: &UserKey,
+    ) -> Result<Vec<IntegrityIssue>, Error<UserError>> {
+        let key = self.derive_graph_key(key);
+        self.findex_graph.findex_mm.verify_integrity(&key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::edx::{
+        audit::AuditedOperation,
+        chain_table::ChainTable,
+        entry_table::EntryTable,
+        faulty::{FaultyDb, FaultyDbError},
+        in_memory::InMemoryDb,
+    };
+
+    #[actix_rt::test]
+    async fn test_search_with_deadline() {
+        let findex = Findex::new(
+            EntryTable::setup(InMemoryDb::default()),
+            ChainTable::setup(InMemoryDb::default()),
+        );
+        let key = findex.keygen();
+        let label = Label::from("label");
+
+        // Each keyword points to the next one: each graph search iteration
+        // reaches one more keyword.
+        let keywords = ["a", "b", "c"].map(Keyword::from);
+        let mut associations = HashMap::new();
+        for (i, keyword) in keywords.iter().enumerate() {
+            associations.insert(
+                IndexedValue::Data(Data::from(format!("location {i}").as_str())),
+                Keywords::from_iter([keyword.clone()]),
+            );
+            if let Some(next_keyword) = keywords.get(i + 1) {
+                associations.insert(
+                    IndexedValue::Pointer(next_keyword.clone()),
+                    Keywords::from_iter([keyword.clone()]),
+                );
+            }
+        }
+        findex
+            .add(&key, &label, IndexedValueToKeywordsMap::from(associations))
+            .await
+            .unwrap();
+
+        // A past deadline stops the traversal after the first iteration.
+        let (res, is_complete) = findex
+            .search_with_deadline(&key, &label, &keywords[0], Instant::now())
+            .await
+            .unwrap();
+        assert!(!is_complete);
+        assert_eq!(res, HashSet::from_iter([Data::from("location 0")]));
+
+        let (res, is_complete) = findex
+            .search_with_deadline(
+                &key,
+                &label,
+                &keywords[0],
+                Instant::now() + Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        assert!(is_complete);
+        assert_eq!(res.len(), 3);
+
+        // A traversal finished after the deadline is complete.
+        let (res, is_complete) = findex
+            .search_with_deadline(&key, &label, &keywords[2], Instant::now())
+            .await
+            .unwrap();
+        assert!(is_complete);
+        assert_eq!(res, HashSet::from_iter([Data::from("location 2")]));
+    }
+
+    #[actix_rt::test]
+    async fn test_connect() {
+        fn unreachable_db<const VALUE_LENGTH: usize>() -> FaultyDb<InMemoryDb<VALUE_LENGTH>> {
+            FaultyDb::scripted(
+                InMemoryDb::default(),
+                (1..=10).map(|i| (AuditedOperation::Fetch, i)),
+            )
+        }
+
+        // The databases are not called upon instantiation.
+        let findex = Findex::new(
+            EntryTable::setup(unreachable_db()),
+            ChainTable::setup(FaultyDb::scripted(InMemoryDb::default(), [])),
+        );
+        let res = findex
+            .search(
+                &findex.keygen(),
+                &Label::from("label"),
+                Keywords::from_iter([Keyword::from("keyword")]),
+                &|_| async { Ok(false) },
+            )
+            .await;
+        assert!(res.is_err());
+
+        let res = Findex::connect(
+            EntryTable::setup(unreachable_db()),
+            ChainTable::setup(FaultyDb::scripted(InMemoryDb::default(), [])),
+        )
+        .await;
+        assert!(
+            matches!(
+                res,
+                Err(Error::DbInterface(FaultyDbError::Injected(
+                    AuditedOperation::Fetch
+                )))
+            ),
+            "{res:?}"
+        );
+
+        let res = Findex::connect(
+            EntryTable::setup(FaultyDb::scripted(InMemoryDb::default(), [])),
+            ChainTable::setup(unreachable_db()),
+        )
+        .await;
+        assert!(res.is_err());
+
+        Findex::connect(
+            EntryTable::setup(FaultyDb::scripted(InMemoryDb::default(), [])),
+            ChainTable::setup(FaultyDb::scripted(InMemoryDb::default(), [])),
+        )
+        .await
+        .unwrap();
+    }
+}
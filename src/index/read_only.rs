@@ -0,0 +1,95 @@
+//! Search-only handle on a `Findex` index.
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+};
+
+use crate::{
+    edx::TokenDump,
+    findex_mm::{ENTRY_LENGTH, LINK_LENGTH},
+    Data, DbInterfaceErrorTrait, DxEnc, Error, Findex, Index, IndexedValue, Keyword,
+    KeywordToDataMap, Keywords, Label, UserKey,
+};
+
+/// Findex handle only allowing to search the index.
+///
+/// It is obtained using [`Findex::read_only()`] and can be used on nodes that
+/// must not modify the index. It does not implement the [`Index`] trait, thus
+/// addition, deletion and compaction are not available:
+#[cfg_attr(feature = "in_memory", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "in_memory"), doc = "```ignore")]
+/// use std::collections::HashSet;
+///
+/// use cosmian_findex::{
+///     ChainTable, Data, DxEnc, EntryTable, Findex, InMemoryDb, Index, IndexedValue,
+///     IndexedValueToKeywordsMap, Keyword, Label,
+/// };
+///
+/// let findex = Findex::new(
+///     EntryTable::setup(InMemoryDb::default()),
+///     ChainTable::setup(InMemoryDb::default()),
+/// );
+/// let key = findex.keygen();
+/// let label = Label::from("label");
+/// let associations = IndexedValueToKeywordsMap::from_iter([(
+///     IndexedValue::Data(Data::from("location")),
+///     HashSet::from_iter([Keyword::from("keyword")]),
+/// )]);
+///
+/// let findex = findex.read_only();
+/// let _ = findex.add(&key, &label, associations);
+/// ```
+#[derive(Debug)]
+pub struct ReadOnlyFindex<
+    UserError: DbInterfaceErrorTrait,
+    EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>>,
+    ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+>(Findex<UserError, EntryTable, ChainTable>);
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+        ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    > Findex<UserError, EntryTable, ChainTable>
+{
+    /// Converts this index into a search-only handle.
+    pub fn read_only(self) -> ReadOnlyFindex<UserError, EntryTable, ChainTable> {
+        ReadOnlyFindex(self)
+    }
+}
+
+impl<
+        UserError: DbInterfaceErrorTrait,
+        EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+        ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+    > ReadOnlyFindex<UserError, EntryTable, ChainTable>
+{
+    /// Searches the index for the given keywords.
+    ///
+    /// See [`Index::search()`].
+    pub async fn search<
+        F: Future<Output = Result<bool, String>>,
+        Interrupt: Fn(HashMap<Keyword, HashSet<IndexedValue<Keyword, Data>>>) -> F,
+    >(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        keywords: Keywords,
+        interrupt: &Interrupt,
+    ) -> Result<KeywordToDataMap, Error<UserError>> {
+        self.0.search(key, label, keywords, interrupt).await
+    }
+
+    /// Returns `true` if the given keyword indexes at least one value.
+    ///
+    /// See [`Findex::contains_keyword()`].
+    pub async fn contains_keyword(
+        &self,
+        key: &UserKey,
+        label: &Label,
+        keyword: &Keyword,
+    ) -> Result<bool, Error<UserError>> {
+        self.0.contains_keyword(key, label, keyword).await
+    }
+}
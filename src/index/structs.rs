@@ -238,3 +238,25 @@ impl<const N: usize> From<[(IndexedValue<Keyword, Data>, Keywords); N]>
         Self(HashMap::from(value))
     }
 }
+
+/// Size of a Findex index.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FindexStats {
+    /// Number of indexed keywords, i.e. number of lines in the Entry Table.
+    pub n_keywords: usize,
+    /// Number of lines in the Chain Table.
+    pub n_links: usize,
+    /// Size in bytes of the tokens and encrypted values stored, not taking
+    /// into account the storage overhead of the database.
+    pub size: usize,
+}
+
+impl Display for FindexStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} keywords, {} links, {} bytes",
+            self.n_keywords, self.n_links, self.size
+        )
+    }
+}
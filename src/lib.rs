@@ -18,19 +18,42 @@ mod findex_graph;
 mod findex_mm;
 mod index;
 mod parameters;
-
+#[cfg(any(test, feature = "test_utils"))]
+pub mod test_utils;
+
+#[cfg(any(test, feature = "append_log"))]
+pub use edx::append_log::{LogDb, LogDbError, LogRecord};
+#[cfg(any(test, feature = "audit"))]
+pub use edx::audit::{AuditOutcome, AuditRecord, AuditSink, AuditedDb, AuditedOperation, VecSink};
+#[cfg(any(test, feature = "cache"))]
+pub use edx::cache::CachedDb;
 #[cfg(any(test, feature = "in_memory"))]
 pub use edx::in_memory::{InMemoryDb, InMemoryDbError};
+#[cfg(any(test, feature = "metered"))]
+pub use edx::metered::{IoStats, MeteredDb};
+#[cfg(any(test, feature = "mirror"))]
+pub use edx::mirror::MirroredDb;
+#[cfg(any(test, feature = "replica"))]
+pub use edx::replica::ReplicatedDb;
+#[cfg(any(test, feature = "shard"))]
+pub use edx::shard::ShardedDb;
+#[cfg(any(test, feature = "test_utils"))]
+pub use edx::{
+    bounded::BoundedDb,
+    faulty::{FaultyDb, FaultyDbError},
+};
 pub use edx::{
-    chain_table::ChainTable, entry_table::EntryTable, DbInterface, DxEnc, EncryptedValue, Token,
-    TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens,
+    chain_table::ChainTable, entry_table::EntryTable, DbInterface, DbSize, DxEnc, EncryptedValue,
+    Token, TokenToEncryptedValueMap, TokenWithEncryptedValueList, Tokens,
 };
 pub use error::{CoreError, DbInterfaceErrorTrait, Error};
 pub use findex_graph::IndexedValue;
-pub use findex_mm::{ENTRY_LENGTH, LINK_LENGTH};
+pub use findex_mm::{CommitStats, IntegrityIssue, SearchPlan, ENTRY_LENGTH, LINK_LENGTH};
+#[cfg(feature = "blocking")]
+pub use index::BlockingFindex;
 pub use index::{
-    Data, Findex, Index, IndexedValueToKeywordsMap, Keyword, KeywordToDataMap, Keywords, Label,
-    UserKey,
+    Data, Findex, FindexStats, Index, IndexedValueToKeywordsMap, Keyword, KeywordToDataMap,
+    Keywords, Label, ReadOnlyFindex, UserKey,
 };
 pub use parameters::*;
 
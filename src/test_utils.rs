@@ -0,0 +1,424 @@
+//! Utilities allowing to test `DbInterface` implementations against a common
+//! scenario.
+
+use std::collections::{HashMap, HashSet};
+
+use cosmian_crypto_core::{
+    reexport::rand_core::{RngCore, SeedableRng},
+    CsRng, Nonce,
+};
+#[doc(hidden)]
+pub use futures_executor::block_on;
+
+use crate::{
+    edx::TokenDump,
+    parameters::{MAC_LENGTH, NONCE_LENGTH},
+    Data, DbInterface, DbInterfaceErrorTrait, DxEnc, EncryptedValue, Error, Findex, Index,
+    IndexedValue, IndexedValueToKeywordsMap, Keyword, Keywords, Label, Token,
+    TokenToEncryptedValueMap, Tokens, ENTRY_LENGTH, LINK_LENGTH,
+};
+
+/// Generates a dataset associating `n_keywords` keywords to
+/// `values_per_keyword` random values each.
+///
+/// The same seed always generates the same dataset.
+#[must_use]
+pub fn generate_dataset(
+    seed: [u8; 32],
+    n_keywords: usize,
+    values_per_keyword: usize,
+) -> HashMap<Keyword, HashSet<Data>> {
+    let mut rng = CsRng::from_seed(seed);
+    (0..n_keywords)
+        .map(|i| {
+            let values = (0..values_per_keyword)
+                .map(|_| {
+                    let mut value = vec![0; 16];
+                    rng.fill_bytes(&mut value);
+                    Data::from(value)
+                })
+                .collect();
+            (Keyword::from(format!("keyword {i}").as_str()), values)
+        })
+        .collect()
+}
+
+/// Indexes the given dataset using a new key and label, searches each of its
+/// keywords and asserts all the values indexed are found.
+///
+/// # Panics
+///
+/// Panics if the search results differ from the dataset.
+pub async fn run_index_roundtrip<
+    UserError: DbInterfaceErrorTrait,
+    EntryTable: DxEnc<ENTRY_LENGTH, Error = Error<UserError>> + TokenDump<Error = Error<UserError>>,
+    ChainTable: DxEnc<LINK_LENGTH, Error = Error<UserError>>,
+>(
+    findex: &Findex<UserError, EntryTable, ChainTable>,
+    dataset: &HashMap<Keyword, HashSet<Data>>,
+) -> Result<(), Error<UserError>> {
+    let key = findex.keygen();
+    let label = Label::random(&mut CsRng::from_entropy());
+
+    let mut associations = HashMap::<_, Keywords>::new();
+    for (keyword, values) in dataset {
+        for value in values {
+            associations
+                .entry(IndexedValue::Data(value.clone()))
+                .or_default()
+                .insert(keyword.clone());
+        }
+    }
+    findex
+        .add(&key, &label, IndexedValueToKeywordsMap::from(associations))
+        .await?;
+
+    let res = findex
+        .search(
+            &key,
+            &label,
+            dataset.keys().cloned().collect(),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+
+    for (keyword, values) in dataset {
+        assert_eq!(
+            Some(values),
+            res.get(keyword),
+            "wrong search results for keyword {keyword}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Generates the `DbInterface` conformance tests for the given database type.
+///
+/// The `setup` expression is evaluated once per test and must return a new
+/// empty database. The generated tests are placed in a `conformance` module:
+///
+/// ```ignore
+/// cosmian_findex::db_interface_conformance_tests!(MyDb<32>, MyDb::new());
+/// ```
+#[macro_export]
+macro_rules! db_interface_conformance_tests {
+    ($db: ty, $setup: expr) => {
+        mod conformance {
+            use super::*;
+
+            #[test]
+            fn test_empty_fetch() {
+                let db: $db = $setup;
+                $crate::test_utils::block_on($crate::test_utils::check_empty_fetch(&db)).unwrap();
+            }
+
+            #[test]
+            fn test_insert_fetch() {
+                let db: $db = $setup;
+                $crate::test_utils::block_on($crate::test_utils::check_insert_fetch(&db)).unwrap();
+            }
+
+            #[test]
+            fn test_upsert_success() {
+                let db: $db = $setup;
+                $crate::test_utils::block_on($crate::test_utils::check_upsert_success(&db))
+                    .unwrap();
+            }
+
+            #[test]
+            fn test_upsert_failure() {
+                let db: $db = $setup;
+                $crate::test_utils::block_on($crate::test_utils::check_upsert_failure(&db))
+                    .unwrap();
+            }
+
+            #[test]
+            fn test_fetch_with_holes() {
+                let db: $db = $setup;
+                $crate::test_utils::block_on($crate::test_utils::check_fetch_with_holes(&db))
+                    .unwrap();
+            }
+
+            #[test]
+            fn test_insert_existing() {
+                let db: $db = $setup;
+                $crate::test_utils::block_on($crate::test_utils::check_insert_existing(&db))
+                    .unwrap();
+            }
+
+            #[test]
+            fn test_upsert_missing_line() {
+                let db: $db = $setup;
+                $crate::test_utils::block_on($crate::test_utils::check_upsert_missing_line(&db))
+                    .unwrap();
+            }
+
+            #[test]
+            fn test_delete() {
+                let db: $db = $setup;
+                $crate::test_utils::block_on($crate::test_utils::check_delete(&db)).unwrap();
+            }
+        }
+    };
+}
+
+/// Generates a random token along with a random value.
+fn random_line<const VALUE_LENGTH: usize>(
+    rng: &mut CsRng,
+) -> (Token, EncryptedValue<VALUE_LENGTH>) {
+    let mut token = [0; Token::LENGTH];
+    rng.fill_bytes(&mut token);
+    let mut value = EncryptedValue {
+        ciphertext: [0; VALUE_LENGTH],
+        tag: [0; MAC_LENGTH],
+        nonce: Nonce::from([0; NONCE_LENGTH]),
+    };
+    rng.fill_bytes(&mut value.ciphertext);
+    (Token::from(token), value)
+}
+
+/// Checks that nothing is fetched from the given empty database.
+///
+/// # Panics
+///
+/// Panics if the database is not empty.
+pub async fn check_empty_fetch<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>>(
+    db: &Db,
+) -> Result<(), Db::Error> {
+    let mut rng = CsRng::from_entropy();
+    let (token, _) = random_line::<VALUE_LENGTH>(&mut rng);
+    assert!(db.dump_tokens().await?.is_empty());
+    assert!(db.fetch(Tokens::from_iter([token])).await?.is_empty());
+    Ok(())
+}
+
+/// Checks that an inserted line is fetched back.
+///
+/// # Panics
+///
+/// Panics if the fetched line differs from the inserted one.
+pub async fn check_insert_fetch<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>>(
+    db: &Db,
+) -> Result<(), Db::Error> {
+    let mut rng = CsRng::from_entropy();
+    let (token, value) = random_line(&mut rng);
+    db.insert(TokenToEncryptedValueMap::from_iter([(
+        token,
+        value.clone(),
+    )]))
+    .await?;
+    assert_eq!(
+        db.fetch(Tokens::from_iter([token])).await?.0,
+        vec![(token, value)]
+    );
+    assert_eq!(db.dump_tokens().await?, Tokens::from_iter([token]));
+    Ok(())
+}
+
+/// Checks that upserts guarded by the stored value, or by the absence of
+/// value, are accepted.
+///
+/// # Panics
+///
+/// Panics if an upsert is rejected or not applied.
+pub async fn check_upsert_success<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>>(
+    db: &Db,
+) -> Result<(), Db::Error> {
+    let mut rng = CsRng::from_entropy();
+    let (token, value) = random_line(&mut rng);
+    let (_, new_value) = random_line(&mut rng);
+
+    let rejected = db
+        .upsert(
+            TokenToEncryptedValueMap::default(),
+            TokenToEncryptedValueMap::from_iter([(token, value.clone())]),
+        )
+        .await?;
+    assert!(rejected.is_empty());
+
+    let rejected = db
+        .upsert(
+            TokenToEncryptedValueMap::from_iter([(token, value)]),
+            TokenToEncryptedValueMap::from_iter([(token, new_value.clone())]),
+        )
+        .await?;
+    assert!(rejected.is_empty());
+    assert_eq!(
+        db.fetch(Tokens::from_iter([token])).await?.0,
+        vec![(token, new_value)]
+    );
+    Ok(())
+}
+
+/// Checks that an upsert guarded by a value other than the stored one is
+/// rejected, and that the stored value is returned.
+///
+/// # Panics
+///
+/// Panics if the upsert is applied or if another value is returned.
+pub async fn check_upsert_failure<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>>(
+    db: &Db,
+) -> Result<(), Db::Error> {
+    let mut rng = CsRng::from_entropy();
+    let (token, stored_value) = random_line(&mut rng);
+    let (_, old_value) = random_line(&mut rng);
+    let (_, new_value) = random_line(&mut rng);
+    db.insert(TokenToEncryptedValueMap::from_iter([(
+        token,
+        stored_value.clone(),
+    )]))
+    .await?;
+
+    let rejected = db
+        .upsert(
+            TokenToEncryptedValueMap::from_iter([(token, old_value)]),
+            TokenToEncryptedValueMap::from_iter([(token, new_value.clone())]),
+        )
+        .await?;
+    assert_eq!(
+        rejected,
+        TokenToEncryptedValueMap::from_iter([(token, stored_value.clone())])
+    );
+
+    // An upsert guarded by the absence of value is rejected too.
+    let rejected = db
+        .upsert(
+            TokenToEncryptedValueMap::default(),
+            TokenToEncryptedValueMap::from_iter([(token, new_value)]),
+        )
+        .await?;
+    assert_eq!(
+        rejected,
+        TokenToEncryptedValueMap::from_iter([(token, stored_value.clone())])
+    );
+
+    assert_eq!(
+        db.fetch(Tokens::from_iter([token])).await?.0,
+        vec![(token, stored_value)]
+    );
+    Ok(())
+}
+
+/// Checks that inserting a line for a token already having a value fails and
+/// leaves the stored value unchanged.
+///
+/// # Panics
+///
+/// Panics if the insertion succeeds or if the stored value is modified.
+pub async fn check_insert_existing<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>>(
+    db: &Db,
+) -> Result<(), Db::Error> {
+    let mut rng = CsRng::from_entropy();
+    let (token, stored_value) = random_line(&mut rng);
+    let (_, new_value) = random_line(&mut rng);
+    db.insert(TokenToEncryptedValueMap::from_iter([(
+        token,
+        stored_value.clone(),
+    )]))
+    .await?;
+
+    assert!(db
+        .insert(TokenToEncryptedValueMap::from_iter([(token, new_value)]))
+        .await
+        .is_err());
+    assert_eq!(
+        db.fetch(Tokens::from_iter([token])).await?.0,
+        vec![(token, stored_value)]
+    );
+    Ok(())
+}
+
+/// Checks that an upsert guarded by a value for a token having no value
+/// fails and writes nothing.
+///
+/// # Panics
+///
+/// Panics if the upsert succeeds or if a value is written.
+pub async fn check_upsert_missing_line<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>>(
+    db: &Db,
+) -> Result<(), Db::Error> {
+    let mut rng = CsRng::from_entropy();
+    let (token, old_value) = random_line(&mut rng);
+    let (_, new_value) = random_line(&mut rng);
+
+    assert!(db
+        .upsert(
+            TokenToEncryptedValueMap::from_iter([(token, old_value)]),
+            TokenToEncryptedValueMap::from_iter([(token, new_value)]),
+        )
+        .await
+        .is_err());
+    assert!(db.fetch(Tokens::from_iter([token])).await?.is_empty());
+    assert!(db.dump_tokens().await?.is_empty());
+    Ok(())
+}
+
+/// Checks that fetching stored and absent tokens at once only returns the
+/// stored lines.
+///
+/// # Panics
+///
+/// Panics if the fetched lines differ from the stored ones.
+pub async fn check_fetch_with_holes<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>>(
+    db: &Db,
+) -> Result<(), Db::Error> {
+    let mut rng = CsRng::from_entropy();
+    let lines = (0..3)
+        .map(|_| random_line(&mut rng))
+        .collect::<HashMap<_, _>>();
+    db.insert(lines.clone().into()).await?;
+
+    let tokens = lines
+        .keys()
+        .copied()
+        .chain((0..2).map(|_| random_line::<VALUE_LENGTH>(&mut rng).0))
+        .collect::<HashSet<_>>();
+    let res = db.fetch(Tokens::from(tokens)).await?;
+    assert_eq!(res.into_iter().collect::<HashMap<_, _>>(), lines);
+    Ok(())
+}
+
+/// Checks that deleted lines are not fetched anymore, and that deleting
+/// absent tokens is not an error.
+///
+/// # Panics
+///
+/// Panics if a deleted line is fetched or if another line is deleted.
+pub async fn check_delete<const VALUE_LENGTH: usize, Db: DbInterface<VALUE_LENGTH>>(
+    db: &Db,
+) -> Result<(), Db::Error> {
+    let mut rng = CsRng::from_entropy();
+    let lines = (0..3).map(|_| random_line(&mut rng)).collect::<Vec<_>>();
+    db.insert(lines.iter().cloned().collect()).await?;
+
+    let (absent_token, _) = random_line::<VALUE_LENGTH>(&mut rng);
+    db.delete(Tokens::from_iter([lines[0].0, lines[1].0, absent_token]))
+        .await?;
+
+    let res = db
+        .fetch(lines.iter().map(|(token, _)| *token).collect())
+        .await?;
+    assert_eq!(res.0, vec![lines[2].clone()]);
+    assert_eq!(db.dump_tokens().await?, Tokens::from_iter([lines[2].0]));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edx::{chain_table::ChainTable, entry_table::EntryTable, in_memory::InMemoryDb};
+
+    #[actix_rt::test]
+    async fn test_index_roundtrip() {
+        let dataset = generate_dataset([1; 32], 100, 10);
+        assert_eq!(dataset, generate_dataset([1; 32], 100, 10));
+        assert_ne!(dataset, generate_dataset([2; 32], 100, 10));
+
+        let findex = Findex::new(
+            EntryTable::setup(InMemoryDb::default()),
+            ChainTable::setup(InMemoryDb::default()),
+        );
+        run_index_roundtrip(&findex, &dataset).await.unwrap();
+    }
+}
@@ -115,23 +115,6 @@ async fn test_non_regression() -> Result<(), Error<InMemoryDbError>> {
     findex.findex_graph.findex_mm.entry_table.0 = de.read::<InMemoryDb<ENTRY_LENGTH>>()?;
     findex.findex_graph.findex_mm.chain_table.0 = de.read::<InMemoryDb<LINK_LENGTH>>()?;
 
-    println!(
-        "Entry Table length: {}",
-        findex.findex_graph.findex_mm.entry_table.len()
-    );
-    println!(
-        "Entry Table size: {}",
-        findex.findex_graph.findex_mm.entry_table.size()
-    );
-    println!(
-        "Chain Table length: {}",
-        findex.findex_graph.findex_mm.chain_table.len()
-    );
-    println!(
-        "Chain Table size: {}",
-        findex.findex_graph.findex_mm.chain_table.size()
-    );
-
     let key = UserKey::try_from_slice(&std::fs::read("datasets/key").unwrap())?;
     let label = Label::from(std::fs::read("datasets/label").unwrap().as_slice());
 
@@ -6,10 +6,15 @@ use std::{
     sync::Arc,
 };
 
-use cosmian_crypto_core::{reexport::rand_core::SeedableRng, CsRng};
+use cosmian_crypto_core::{
+    bytes_ser_de::{Deserializer, Serializer},
+    reexport::rand_core::SeedableRng,
+    CsRng,
+};
 use cosmian_findex::{
-    ChainTable, Data, DxEnc, EntryTable, Error, Findex, InMemoryDb, InMemoryDbError, Index,
-    IndexedValue, IndexedValueToKeywordsMap, Keyword, Keywords, Label,
+    ChainTable, Data, DxEnc, EncryptedValue, EntryTable, Error, Findex, FindexStats, InMemoryDb,
+    InMemoryDbError, Index, IndexedValue, IndexedValueToKeywordsMap, IntegrityIssue, Keyword,
+    Keywords, Label, SearchPlan, Token, UserKey, ENTRY_LENGTH, LINK_LENGTH,
 };
 use futures::executor::block_on;
 use rand::Rng;
@@ -76,6 +81,54 @@ fn check_search_result(
     }
 }
 
+/// Indexes the given locations for the given keyword, one addition at a time,
+/// then removes the link of the second location from the Chain Table, as a
+/// failed addition may leave it.
+async fn add_with_missing_link(
+    findex: &Findex<
+        InMemoryDbError,
+        EntryTable<ENTRY_LENGTH, InMemoryDb<ENTRY_LENGTH>>,
+        ChainTable<LINK_LENGTH, InMemoryDb<LINK_LENGTH>>,
+    >,
+    key: &UserKey,
+    label: &Label,
+    keyword: &Keyword,
+    locations: [&str; 3],
+) -> Result<(), Error<InMemoryDbError>> {
+    let chain_table = &findex.findex_graph.findex_mm.chain_table.0;
+    let mut missing_links = Vec::new();
+    for (i, location) in locations.into_iter().enumerate() {
+        let old_tokens = chain_table
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<HashSet<_>>();
+        findex
+            .add(
+                key,
+                label,
+                IndexedValueToKeywordsMap::from([(
+                    IndexedValue::Data(Data::from(location)),
+                    Keywords::from_iter([keyword.clone()]),
+                )]),
+            )
+            .await?;
+        if 1 == i {
+            missing_links = chain_table
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|token| !old_tokens.contains(token))
+                .copied()
+                .collect();
+        }
+    }
+    assert_eq!(1, missing_links.len());
+    chain_table.lock().unwrap().remove(&missing_links[0]);
+    Ok(())
+}
+
 /// Checks the `progress` callback works.
 ///
 /// The results returned by the callback for a "rob" search should contain
@@ -1172,3 +1225,1247 @@ async fn test_concurrency() -> Result<(), Error<InMemoryDbError>> {
 
     Ok(())
 }
+
+#[actix_rt::test]
+async fn test_key_rotation() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let old_key = findex.keygen();
+    let new_key = findex.keygen();
+    let label = Label::from("First label.");
+
+    let keyword = Keyword::from("robert");
+    let location = Data::from("robert doe DB location");
+    findex
+        .add(
+            &old_key,
+            &label,
+            IndexedValueToKeywordsMap::from([(
+                IndexedValue::Data(location.clone()),
+                Keywords::from_iter([keyword.clone()]),
+            )]),
+        )
+        .await?;
+
+    let ct_length = findex.findex_graph.findex_mm.chain_table.len();
+
+    findex
+        .rotate_key(&old_key, &new_key, &label, &label)
+        .await?;
+
+    // The Chain Table is not modified by a key rotation.
+    assert_eq!(ct_length, findex.findex_graph.findex_mm.chain_table.len());
+    assert_eq!(1, findex.findex_graph.findex_mm.entry_table.len());
+
+    let res = findex
+        .search(
+            &new_key,
+            &label,
+            Keywords::from_iter([keyword.clone()]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    check_search_result(&res, &keyword, &location).unwrap();
+
+    let res = findex
+        .search(
+            &old_key,
+            &label,
+            Keywords::from_iter([keyword.clone()]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    assert_eq!(res.get(&keyword), Some(&HashSet::new()));
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_compact_reclaims_deletions() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+    let keyword = Keyword::from("keyword");
+
+    // Each value fits in a single block: a link holds `LINE_WIDTH` of them.
+    let locations = (0..100)
+        .map(|i| Data::from(format!("Value {i:02}").as_str()))
+        .collect::<Vec<_>>();
+
+    findex
+        .add(
+            &key,
+            &label,
+            locations
+                .iter()
+                .map(|location| {
+                    (
+                        IndexedValue::Data(location.clone()),
+                        Keywords::from_iter([keyword.clone()]),
+                    )
+                })
+                .collect(),
+        )
+        .await?;
+    findex
+        .delete(
+            &key,
+            &label,
+            locations[..50]
+                .iter()
+                .map(|location| {
+                    (
+                        IndexedValue::Data(location.clone()),
+                        Keywords::from_iter([keyword.clone()]),
+                    )
+                })
+                .collect(),
+        )
+        .await?;
+
+    // 20 links hold the additions and 10 links hold the deletions.
+    assert_eq!(30, findex.findex_graph.findex_mm.chain_table.len());
+
+    let new_label = Label::from("Second label.");
+    findex
+        .compact(&key, &key, &label, &new_label, 1f64, &|data| async {
+            Ok(data)
+        })
+        .await?;
+
+    // Only the 10 links holding the remaining values are left.
+    assert_eq!(10, findex.findex_graph.findex_mm.chain_table.len());
+
+    let res = findex
+        .search(
+            &key,
+            &new_label,
+            Keywords::from_iter([keyword.clone()]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    assert_eq!(
+        res.get(&keyword),
+        Some(&locations[50..].iter().cloned().collect())
+    );
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_contains_keyword() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+
+    let robert_keyword = Keyword::from("robert");
+    let john_keyword = Keyword::from("john");
+    let absent_keyword = Keyword::from("absent");
+    let robert_location = IndexedValue::Data(Data::from("robert doe DB location"));
+    let john_location = IndexedValue::Data(Data::from("john doe DB location"));
+
+    findex
+        .add(
+            &key,
+            &label,
+            IndexedValueToKeywordsMap::from([
+                (
+                    robert_location,
+                    Keywords::from_iter([robert_keyword.clone()]),
+                ),
+                (
+                    john_location.clone(),
+                    Keywords::from_iter([john_keyword.clone()]),
+                ),
+            ]),
+        )
+        .await?;
+    findex
+        .delete(
+            &key,
+            &label,
+            IndexedValueToKeywordsMap::from([(
+                john_location,
+                Keywords::from_iter([john_keyword.clone()]),
+            )]),
+        )
+        .await?;
+
+    assert!(
+        findex
+            .contains_keyword(&key, &label, &robert_keyword)
+            .await?
+    );
+    assert!(!findex.contains_keyword(&key, &label, &john_keyword).await?);
+    assert!(
+        !findex
+            .contains_keyword(&key, &label, &absent_keyword)
+            .await?
+    );
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_serialization() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+
+    let mut indexed_value_to_keywords = HashMap::new();
+    let robert_keyword = Keyword::from("robert");
+    let robert_doe_location = Data::from("robert doe DB location");
+    indexed_value_to_keywords.insert(
+        IndexedValue::Data(robert_doe_location.clone()),
+        Keywords::from_iter([robert_keyword.clone(), Keyword::from("doe")]),
+    );
+    add_keyword_graph(
+        &robert_keyword,
+        MIN_KEYWORD_LENGTH,
+        &mut indexed_value_to_keywords,
+    );
+    findex
+        .add(
+            &key,
+            &label,
+            IndexedValueToKeywordsMap::from(indexed_value_to_keywords),
+        )
+        .await?;
+
+    let mut ser = Serializer::new();
+    ser.write(&findex.findex_graph.findex_mm.entry_table.0)?;
+    ser.write(&findex.findex_graph.findex_mm.chain_table.0)?;
+    let bytes = ser.finalize();
+
+    let mut snapshot = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+    let mut de = Deserializer::new(&bytes);
+    snapshot.findex_graph.findex_mm.entry_table.0 = de.read::<InMemoryDb<ENTRY_LENGTH>>()?;
+    snapshot.findex_graph.findex_mm.chain_table.0 = de.read::<InMemoryDb<LINK_LENGTH>>()?;
+
+    let keywords = Keywords::from_iter(["rob", "robert", "doe"]);
+    let res = findex
+        .search(&key, &label, keywords.clone(), &|_| async { Ok(false) })
+        .await?;
+    let snapshot_res = snapshot
+        .search(&key, &label, keywords, &|_| async { Ok(false) })
+        .await?;
+    assert_eq!(res, snapshot_res);
+    check_search_result(&snapshot_res, &Keyword::from("rob"), &robert_doe_location).unwrap();
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_read_only() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+    let keyword = Keyword::from("robert");
+    let location = Data::from("robert doe DB location");
+
+    findex
+        .add(
+            &key,
+            &label,
+            IndexedValueToKeywordsMap::from([(
+                IndexedValue::Data(location.clone()),
+                Keywords::from_iter([keyword.clone()]),
+            )]),
+        )
+        .await?;
+
+    let findex = findex.read_only();
+
+    let res = findex
+        .search(
+            &key,
+            &label,
+            Keywords::from_iter([keyword.clone()]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    check_search_result(&res, &keyword, &location).unwrap();
+    assert!(findex.contains_keyword(&key, &label, &keyword).await?);
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_dump_tokens() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+
+    assert!(findex.dump_tokens().await?.is_empty());
+
+    let n_keywords = 10;
+    for i in 0..2 {
+        findex
+            .add(
+                &key,
+                &label,
+                IndexedValueToKeywordsMap::from_iter((0..n_keywords).map(|j| {
+                    (
+                        IndexedValue::Data(Data::from(format!("location {i} {j}").as_str())),
+                        Keywords::from_iter([Keyword::from(format!("keyword {j}").as_str())]),
+                    )
+                })),
+            )
+            .await?;
+
+        // Adding values to already indexed keywords does not add new tokens.
+        assert_eq!(n_keywords, findex.dump_tokens().await?.len());
+    }
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_new_with_rng() -> Result<(), Error<InMemoryDbError>> {
+    let seed = [42; 32];
+
+    let mut tables = Vec::new();
+    for _ in 0..2 {
+        let findex = Findex::new_with_rng(
+            EntryTable::setup(InMemoryDb::default()),
+            ChainTable::setup(InMemoryDb::default()),
+            CsRng::from_seed(seed),
+        );
+        let key = findex.keygen();
+        let label = Label::from("First label.");
+
+        for i in 0..3 {
+            findex
+                .add(
+                    &key,
+                    &label,
+                    IndexedValueToKeywordsMap::from([(
+                        IndexedValue::Data(Data::from(format!("location {i}").as_str())),
+                        Keywords::from_iter([Keyword::from("robert")]),
+                    )]),
+                )
+                .await?;
+        }
+
+        let entry_table = findex
+            .findex_graph
+            .findex_mm
+            .entry_table
+            .0
+            .lock()
+            .unwrap()
+            .clone();
+        let chain_table = findex
+            .findex_graph
+            .findex_mm
+            .chain_table
+            .0
+            .lock()
+            .unwrap()
+            .clone();
+        tables.push((entry_table, chain_table));
+    }
+
+    assert_eq!(1, tables[0].0.len());
+    assert_eq!(3, tables[0].1.len());
+    assert_eq!(tables[0], tables[1]);
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_stats() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+
+    assert_eq!(FindexStats::default(), findex.stats().await?);
+
+    let robert_keyword = Keyword::from("robert");
+    let john_keyword = Keyword::from("john");
+
+    // Two keywords are indexed. Since the values associated to `robert` are
+    // indexed in the same operation and are small enough, they fit in a single
+    // link.
+    findex
+        .add(
+            &key,
+            &label,
+            IndexedValueToKeywordsMap::from([
+                (
+                    IndexedValue::Data(Data::from("robert doe DB location")),
+                    Keywords::from_iter([robert_keyword.clone()]),
+                ),
+                (
+                    IndexedValue::Pointer(john_keyword.clone()),
+                    Keywords::from_iter([robert_keyword.clone()]),
+                ),
+                (
+                    IndexedValue::Data(Data::from("john doe DB location")),
+                    Keywords::from_iter([john_keyword]),
+                ),
+            ]),
+        )
+        .await?;
+
+    // A new link is added to the chain of `robert`.
+    findex
+        .delete(
+            &key,
+            &label,
+            IndexedValueToKeywordsMap::from([(
+                IndexedValue::Data(Data::from("robert doe DB location")),
+                Keywords::from_iter([robert_keyword]),
+            )]),
+        )
+        .await?;
+
+    let stats = findex.stats().await?;
+    assert_eq!(2, stats.n_keywords);
+    assert_eq!(3, stats.n_links);
+    assert_eq!(
+        findex.findex_graph.findex_mm.entry_table.size()
+            + findex.findex_graph.findex_mm.chain_table.size(),
+        stats.size
+    );
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_compact_keyword() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+
+    let status_keyword = Keyword::from("status");
+    let john_keyword = Keyword::from("john");
+    let john_location = Data::from("john doe DB location");
+
+    findex
+        .add(
+            &key,
+            &label,
+            IndexedValueToKeywordsMap::from([(
+                IndexedValue::Data(john_location.clone()),
+                Keywords::from_iter([john_keyword.clone()]),
+            )]),
+        )
+        .await?;
+
+    // Toggle the status of 10 documents, only the last one remains.
+    for i in 0..10 {
+        let associations = IndexedValueToKeywordsMap::from([(
+            IndexedValue::Data(Data::from(format!("document {i}").as_str())),
+            Keywords::from_iter([status_keyword.clone()]),
+        )]);
+        findex.add(&key, &label, associations.clone()).await?;
+        if i != 9 {
+            findex.delete(&key, &label, associations).await?;
+        }
+    }
+    assert_eq!(20, findex.findex_graph.findex_mm.chain_table.len());
+
+    let search = || {
+        findex.search(
+            &key,
+            &label,
+            Keywords::from_iter([status_keyword.clone(), john_keyword.clone()]),
+            &|_| async { Ok(false) },
+        )
+    };
+    let res = search().await?;
+
+    findex
+        .compact_keyword(&key, &label, &status_keyword)
+        .await?;
+
+    // Only one link remains for `status` and the chain of `john` is untouched.
+    assert_eq!(2, findex.findex_graph.findex_mm.entry_table.len());
+    assert_eq!(2, findex.findex_graph.findex_mm.chain_table.len());
+    assert_eq!(res, search().await?);
+    check_search_result(&res, &status_keyword, &Data::from("document 9")).unwrap();
+    check_search_result(&res, &john_keyword, &john_location).unwrap();
+
+    // Compacting an absent keyword does nothing.
+    findex
+        .compact_keyword(&key, &label, &Keyword::from("absent"))
+        .await?;
+    assert_eq!(2, findex.findex_graph.findex_mm.chain_table.len());
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_compact_keyword_missing_link() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+    let keyword = Keyword::from("robert");
+    add_with_missing_link(
+        &findex,
+        &key,
+        &label,
+        &keyword,
+        ["location 0", "location 1", "location 2"],
+    )
+    .await?;
+
+    // The missing link is skipped by the search.
+    let search = || {
+        findex.search(
+            &key,
+            &label,
+            Keywords::from_iter([keyword.clone()]),
+            &|_| async { Ok(false) },
+        )
+    };
+    let res = search().await?;
+    assert_eq!(
+        res[&keyword],
+        HashSet::from_iter([Data::from("location 0"), Data::from("location 2")])
+    );
+
+    // The compaction cannot tell a lost link from a link still being written:
+    // the chain is not modified.
+    let chain_table_length = findex.findex_graph.findex_mm.chain_table.len();
+    let compaction = findex.compact_keyword(&key, &label, &keyword).await;
+    assert!(
+        matches!(compaction, Err(Error::MissingLinks(1))),
+        "{compaction:?}"
+    );
+    assert_eq!(
+        chain_table_length,
+        findex.findex_graph.findex_mm.chain_table.len()
+    );
+    assert_eq!(res, search().await?);
+    assert_eq!(1, findex.verify_integrity(&key).await?.len());
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_verify_integrity() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+
+    findex
+        .add(
+            &key,
+            &label,
+            IndexedValueToKeywordsMap::from_iter((0..3).map(|i| {
+                (
+                    IndexedValue::Data(Data::from(format!("location {i}").as_str())),
+                    Keywords::from_iter([Keyword::from(format!("keyword {i}").as_str())]),
+                )
+            })),
+        )
+        .await?;
+    assert!(findex.verify_integrity(&key).await?.is_empty());
+
+    let entry_table = &findex.findex_graph.findex_mm.entry_table.0;
+    let chain_table = &findex.findex_graph.findex_mm.chain_table.0;
+    let (link, value) = chain_table
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(token, value)| (*token, value.clone()))
+        .next()
+        .unwrap();
+
+    // Corrupt a link.
+    chain_table
+        .lock()
+        .unwrap()
+        .get_mut(&link)
+        .unwrap()
+        .ciphertext[0] ^= 1;
+    let issues = findex.verify_integrity(&key).await?;
+    assert!(
+        matches!(issues[..], [IntegrityIssue::UndecryptableLink { link: l, .. }] if l == link),
+        "{issues:?}"
+    );
+
+    // Delete a link.
+    chain_table.lock().unwrap().remove(&link);
+    let issues = findex.verify_integrity(&key).await?;
+    assert!(
+        matches!(issues[..], [IntegrityIssue::MissingLink { link: l, .. }] if l == link),
+        "{issues:?}"
+    );
+    chain_table.lock().unwrap().insert(link, value.clone());
+    assert!(findex.verify_integrity(&key).await?.is_empty());
+
+    // Add an unreferenced link.
+    let orphan = Token::from([0; Token::LENGTH]);
+    chain_table.lock().unwrap().insert(orphan, value);
+    let issues = findex.verify_integrity(&key).await?;
+    assert_eq!(issues, vec![IntegrityIssue::OrphanLink(orphan)]);
+    chain_table.lock().unwrap().remove(&orphan);
+
+    // Corrupt an entry: its chain is not referenced anymore.
+    let entry = *entry_table.lock().unwrap().keys().next().unwrap();
+    entry_table.lock().unwrap().get_mut(&entry).unwrap().tag[0] ^= 1;
+    let issues = findex.verify_integrity(&key).await?;
+    assert_eq!(2, issues.len());
+    assert!(issues.contains(&IntegrityIssue::UndecryptableEntry(entry)));
+    assert!(matches!(
+        issues
+            .iter()
+            .find(|issue| **issue != IntegrityIssue::UndecryptableEntry(entry)),
+        Some(IntegrityIssue::OrphanLink(_))
+    ));
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_rebuild() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let old_key = findex.keygen();
+    let new_key = findex.keygen();
+    let old_label = Label::from("First label.");
+    let new_label = Label::from("Second label.");
+
+    let keyword = Keyword::from("robert");
+    let associations = |location: &str| {
+        IndexedValueToKeywordsMap::from([(
+            IndexedValue::Data(Data::from(location)),
+            Keywords::from_iter([keyword.clone()]),
+        )])
+    };
+    findex
+        .add(&old_key, &old_label, associations("robert doe DB location"))
+        .await?;
+    findex
+        .add(&old_key, &old_label, associations("deleted location"))
+        .await?;
+    findex
+        .delete(&old_key, &old_label, associations("deleted location"))
+        .await?;
+
+    let entry_table = InMemoryDb::default();
+    let chain_table = InMemoryDb::default();
+    let new_findex = findex
+        .rebuild(
+            &old_key,
+            &new_key,
+            &new_label,
+            EntryTable::setup(entry_table.clone()),
+            ChainTable::setup(chain_table.clone()),
+        )
+        .await?;
+    assert_eq!(1, entry_table.len());
+    let ct_length = chain_table.len();
+
+    async fn search(
+        findex: &Findex<
+            InMemoryDbError,
+            EntryTable<ENTRY_LENGTH, InMemoryDb<ENTRY_LENGTH>>,
+            ChainTable<LINK_LENGTH, InMemoryDb<LINK_LENGTH>>,
+        >,
+        key: &UserKey,
+        label: &Label,
+    ) -> Result<HashSet<Data>, Error<InMemoryDbError>> {
+        let keyword = Keyword::from("robert");
+        let mut res = findex
+            .search(
+                key,
+                label,
+                Keywords::from_iter([keyword.clone()]),
+                &|_| async { Ok(false) },
+            )
+            .await?;
+        Ok(res.remove(&keyword).unwrap_or_default())
+    }
+    assert_eq!(
+        search(&new_findex, &new_key, &new_label).await?,
+        HashSet::from_iter([Data::from("robert doe DB location")])
+    );
+    assert!(search(&new_findex, &old_key, &new_label).await?.is_empty());
+    assert!(search(&new_findex, &new_key, &old_label).await?.is_empty());
+
+    // The old index is not modified.
+    assert_eq!(1, search(&findex, &old_key, &old_label).await?.len());
+
+    // Rebuilding again does not duplicate the migrated keywords.
+    findex
+        .rebuild(
+            &old_key,
+            &new_key,
+            &new_label,
+            EntryTable::setup(entry_table.clone()),
+            ChainTable::setup(chain_table.clone()),
+        )
+        .await?;
+    assert_eq!(1, entry_table.len());
+    assert_eq!(ct_length, chain_table.len());
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_rebuild_missing_link() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+    let key = findex.keygen();
+    let new_key = findex.keygen();
+    let label = Label::from("First label.");
+    let keyword = Keyword::from("robert");
+    add_with_missing_link(
+        &findex,
+        &key,
+        &label,
+        &keyword,
+        ["location 0", "location 1", "location 2"],
+    )
+    .await?;
+
+    // The missing link is not copied.
+    let new_findex = findex
+        .rebuild(
+            &key,
+            &new_key,
+            &label,
+            EntryTable::setup(InMemoryDb::default()),
+            ChainTable::setup(InMemoryDb::default()),
+        )
+        .await?;
+    let res = new_findex
+        .search(
+            &new_key,
+            &label,
+            Keywords::from_iter([keyword.clone()]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    assert_eq!(
+        res[&keyword],
+        HashSet::from_iter([Data::from("location 0"), Data::from("location 2")])
+    );
+    assert!(new_findex.verify_integrity(&new_key).await?.is_empty());
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_input_validation() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+
+    let associations = |value: IndexedValue<Keyword, Data>, keyword: &str| {
+        IndexedValueToKeywordsMap::from([(value, Keywords::from_iter([Keyword::from(keyword)]))])
+    };
+    let location = IndexedValue::Data(Data::from("location"));
+
+    let res = findex
+        .add(&key, &label, associations(location.clone(), ""))
+        .await;
+    assert!(matches!(res, Err(Error::EmptyKeyword)), "{res:?}");
+
+    let res = findex
+        .delete(&key, &label, associations(location, ""))
+        .await;
+    assert!(matches!(res, Err(Error::EmptyKeyword)), "{res:?}");
+
+    let res = findex
+        .add(
+            &key,
+            &label,
+            associations(IndexedValue::Pointer(Keyword::from("")), "keyword"),
+        )
+        .await;
+    assert!(matches!(res, Err(Error::EmptyKeyword)), "{res:?}");
+
+    let res = findex
+        .search(
+            &key,
+            &label,
+            Keywords::from_iter([Keyword::from("")]),
+            &|_| async { Ok(false) },
+        )
+        .await;
+    assert!(matches!(res, Err(Error::EmptyKeyword)), "{res:?}");
+
+    let empty_keyword = Keyword::from("");
+    let res = findex.contains_keyword(&key, &label, &empty_keyword).await;
+    assert!(matches!(res, Err(Error::EmptyKeyword)), "{res:?}");
+
+    let res = findex.delete_keyword(&key, &label, &empty_keyword).await;
+    assert!(matches!(res, Err(Error::EmptyKeyword)), "{res:?}");
+
+    let res = findex.compact_keyword(&key, &label, &empty_keyword).await;
+    assert!(matches!(res, Err(Error::EmptyKeyword)), "{res:?}");
+
+    // Nothing was written.
+    assert_eq!(0, findex.findex_graph.findex_mm.entry_table.len());
+
+    // Values of any length can be indexed.
+    let long_value = Data::from(vec![0; 1 << 16]);
+    findex
+        .add(
+            &key,
+            &label,
+            associations(IndexedValue::Data(long_value.clone()), "keyword"),
+        )
+        .await?;
+    let res = findex
+        .search(
+            &key,
+            &label,
+            Keywords::from_iter([Keyword::from("keyword")]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    assert_eq!(
+        Some(&HashSet::from([long_value.clone()])),
+        res.get(&Keyword::from("keyword"))
+    );
+
+    findex
+        .delete(
+            &key,
+            &label,
+            associations(IndexedValue::Data(long_value), "keyword"),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Checks indexes sharing the same tables and key but using different labels
+/// do not see each other's keywords.
+#[actix_rt::test]
+async fn test_label_isolation() -> Result<(), Error<InMemoryDbError>> {
+    let entry_table = InMemoryDb::default();
+    let chain_table = InMemoryDb::default();
+    let findex = Findex::new(
+        EntryTable::setup(entry_table.clone()),
+        ChainTable::setup(chain_table.clone()),
+    );
+    let other_findex = Findex::new(
+        EntryTable::setup(entry_table.clone()),
+        ChainTable::setup(chain_table.clone()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First index.");
+    let other_label = Label::from("Second index.");
+    let keyword = Keyword::from("robert");
+
+    for (findex, label, location) in [
+        (&findex, &label, "first location"),
+        (&other_findex, &other_label, "second location"),
+    ] {
+        findex
+            .add(
+                &key,
+                label,
+                IndexedValueToKeywordsMap::from([(
+                    IndexedValue::Data(Data::from(location)),
+                    Keywords::from_iter([keyword.clone()]),
+                )]),
+            )
+            .await?;
+    }
+    assert_eq!(2, entry_table.len());
+
+    for (findex, label, location) in [
+        (&findex, &label, "first location"),
+        (&other_findex, &other_label, "second location"),
+    ] {
+        let res = findex
+            .search(
+                &key,
+                label,
+                Keywords::from_iter([keyword.clone()]),
+                &|_| async { Ok(false) },
+            )
+            .await?;
+        assert_eq!(
+            res.get(&keyword),
+            Some(&HashSet::from_iter([Data::from(location)]))
+        );
+    }
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_delete_keyword() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+
+    let robert_keyword = Keyword::from("robert");
+    let rob_keyword = Keyword::from("rob");
+    let john_keyword = Keyword::from("john");
+    let robert_location = Data::from("robert doe DB location");
+    let john_location = Data::from("john doe DB location");
+
+    let mut associations = HashMap::new();
+    for i in 0..5 {
+        associations.insert(
+            IndexedValue::Data(Data::from(format!("robert location {i}").as_str())),
+            Keywords::from_iter([robert_keyword.clone()]),
+        );
+    }
+    associations.insert(
+        IndexedValue::Data(robert_location.clone()),
+        Keywords::from_iter([robert_keyword.clone(), john_keyword.clone()]),
+    );
+    associations.insert(
+        IndexedValue::Data(john_location.clone()),
+        Keywords::from_iter([john_keyword.clone()]),
+    );
+    associations.insert(
+        IndexedValue::Pointer(robert_keyword.clone()),
+        Keywords::from_iter([rob_keyword.clone()]),
+    );
+    findex
+        .add(&key, &label, IndexedValueToKeywordsMap::from(associations))
+        .await?;
+
+    assert!(findex.delete_keyword(&key, &label, &robert_keyword).await?);
+    assert!(!findex.delete_keyword(&key, &label, &robert_keyword).await?);
+
+    let res = findex
+        .search(
+            &key,
+            &label,
+            Keywords::from_iter([
+                robert_keyword.clone(),
+                rob_keyword.clone(),
+                john_keyword.clone(),
+            ]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    assert_eq!(res.get(&robert_keyword), Some(&HashSet::new()));
+    assert_eq!(res.get(&rob_keyword), Some(&HashSet::new()));
+    assert_eq!(
+        res.get(&john_keyword),
+        Some(&HashSet::from_iter([robert_location, john_location]))
+    );
+
+    // The pointer of the "rob" keyword is kept.
+    assert!(findex.contains_keyword(&key, &label, &rob_keyword).await?);
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_explain_search() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+
+    let robert_keyword = Keyword::from("robert");
+    let rob_keyword = Keyword::from("rob");
+
+    // Ten short values fill two links of the chain of `robert`.
+    let mut associations = HashMap::new();
+    for i in 0..10 {
+        associations.insert(
+            IndexedValue::Data(Data::from(format!("loc {i}").as_str())),
+            Keywords::from_iter([robert_keyword.clone()]),
+        );
+    }
+    associations.insert(
+        IndexedValue::Pointer(robert_keyword.clone()),
+        Keywords::from_iter([rob_keyword.clone()]),
+    );
+    findex
+        .add(&key, &label, IndexedValueToKeywordsMap::from(associations))
+        .await?;
+
+    let entry_line_length = Token::LENGTH + EncryptedValue::<ENTRY_LENGTH>::LENGTH;
+    let link_line_length = Token::LENGTH + EncryptedValue::<LINK_LENGTH>::LENGTH;
+    let plan = findex.explain_search(&key, &label, &robert_keyword).await?;
+    assert_eq!(
+        plan,
+        SearchPlan {
+            round_trips: 2,
+            addresses_read: 3,
+            chain_length: 2,
+            bytes: entry_line_length + 2 * link_line_length,
+        }
+    );
+
+    // The pointer from `rob` to `robert` is followed.
+    let plan = findex.explain_search(&key, &label, &rob_keyword).await?;
+    assert_eq!(plan.round_trips, 4);
+    assert_eq!(plan.chain_length, 3);
+
+    // No link is read for a keyword that is not indexed.
+    let plan = findex
+        .explain_search(&key, &label, &Keyword::from("john"))
+        .await?;
+    assert_eq!(
+        plan,
+        SearchPlan {
+            round_trips: 2,
+            addresses_read: 1,
+            chain_length: 0,
+            bytes: 0,
+        }
+    );
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_search_filter() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+    let keyword = Keyword::from("robert");
+
+    // Values are tagged by their first byte.
+    let mut associations = HashMap::new();
+    for i in 0..10 {
+        for tag in [b'u', b'g'] {
+            let mut value = vec![tag];
+            value.extend(format!("location {i}").as_bytes());
+            associations.insert(
+                IndexedValue::Data(Data::from(value)),
+                Keywords::from_iter([keyword.clone()]),
+            );
+        }
+    }
+    findex
+        .add(&key, &label, IndexedValueToKeywordsMap::from(associations))
+        .await?;
+
+    let is_user = |data: &Data| data.first() == Some(&b'u');
+    let res = findex
+        .search_filter(&key, &label, &keyword, is_user, usize::MAX)
+        .await?;
+    assert_eq!(res.len(), 10);
+    assert!(res.iter().all(is_user));
+
+    let res = findex
+        .search_filter(&key, &label, &keyword, is_user, 3)
+        .await?;
+    assert_eq!(res.len(), 3);
+    assert!(res.iter().all(is_user));
+
+    let res = findex
+        .search_filter(&key, &label, &Keyword::from("john"), is_user, 3)
+        .await?;
+    assert!(res.is_empty());
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_merge() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+    let other_findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+    let associations = |keyword: &str, locations: &[&str]| {
+        IndexedValueToKeywordsMap::from_iter(locations.iter().map(|location| {
+            (
+                IndexedValue::Data(Data::from(*location)),
+                Keywords::from_iter([Keyword::from(keyword)]),
+            )
+        }))
+    };
+
+    findex
+        .add(&key, &label, associations("robert", &["location 1"]))
+        .await?;
+    findex
+        .add(
+            &key,
+            &label,
+            associations("shared", &["location 1", "location 2"]),
+        )
+        .await?;
+    other_findex
+        .add(&key, &label, associations("john", &["location 3"]))
+        .await?;
+    other_findex
+        .add(
+            &key,
+            &label,
+            associations("shared", &["location 2", "location 3"]),
+        )
+        .await?;
+    other_findex
+        .delete(&key, &label, associations("shared", &["location 3"]))
+        .await?;
+
+    let chain_table_length = findex.findex_graph.findex_mm.chain_table.len();
+    findex.merge(&key, &label, &other_findex).await?;
+
+    // Only the association of "john" is new: "location 2" is already indexed
+    // by "shared".
+    assert_eq!(
+        chain_table_length + 1,
+        findex.findex_graph.findex_mm.chain_table.len()
+    );
+
+    // Merging again adds nothing.
+    let chain_table_length = findex.findex_graph.findex_mm.chain_table.len();
+    findex.merge(&key, &label, &other_findex).await?;
+    assert_eq!(
+        chain_table_length,
+        findex.findex_graph.findex_mm.chain_table.len()
+    );
+
+    let res = findex
+        .search(
+            &key,
+            &label,
+            Keywords::from_iter([
+                Keyword::from("robert"),
+                Keyword::from("john"),
+                Keyword::from("shared"),
+            ]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    assert_eq!(
+        res.get(&Keyword::from("robert")),
+        Some(&HashSet::from_iter([Data::from("location 1")]))
+    );
+    assert_eq!(
+        res.get(&Keyword::from("john")),
+        Some(&HashSet::from_iter([Data::from("location 3")]))
+    );
+    // Deleted associations are not merged.
+    assert_eq!(
+        res.get(&Keyword::from("shared")),
+        Some(&HashSet::from_iter([
+            Data::from("location 1"),
+            Data::from("location 2")
+        ]))
+    );
+
+    // The other index is not modified.
+    let res = other_findex
+        .search(
+            &key,
+            &label,
+            Keywords::from_iter([Keyword::from("robert")]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    assert_eq!(res.get(&Keyword::from("robert")), Some(&HashSet::new()));
+
+    Ok(())
+}
+
+#[actix_rt::test]
+async fn test_merge_missing_link() -> Result<(), Error<InMemoryDbError>> {
+    let findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+    let other_findex = Findex::new(
+        EntryTable::setup(InMemoryDb::default()),
+        ChainTable::setup(InMemoryDb::default()),
+    );
+    let key = findex.keygen();
+    let label = Label::from("First label.");
+    let keyword = Keyword::from("robert");
+    add_with_missing_link(
+        &other_findex,
+        &key,
+        &label,
+        &keyword,
+        ["location 0", "location 1", "location 2"],
+    )
+    .await?;
+
+    // The missing link is not copied.
+    findex.merge(&key, &label, &other_findex).await?;
+    let res = findex
+        .search(
+            &key,
+            &label,
+            Keywords::from_iter([keyword.clone()]),
+            &|_| async { Ok(false) },
+        )
+        .await?;
+    assert_eq!(
+        res[&keyword],
+        HashSet::from_iter([Data::from("location 0"), Data::from("location 2")])
+    );
+    assert!(findex.verify_integrity(&key).await?.is_empty());
+
+    Ok(())
+}